@@ -0,0 +1 @@
+import { format } from "./format";
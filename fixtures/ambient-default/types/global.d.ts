@@ -0,0 +1 @@
+declare const __DEV__: boolean;
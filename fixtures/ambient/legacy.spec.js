@@ -0,0 +1,5 @@
+import { legacy } from "./src/legacy/old";
+
+test("legacy", () => {
+	expect(legacy).toBe(true);
+});
@@ -0,0 +1,5 @@
+import { formatVersion } from "./src/format";
+
+test("formatVersion", () => {
+	expect(typeof formatVersion()).toBe("string");
+});
@@ -0,0 +1,3 @@
+export function formatVersion(): string {
+	return `v${__APP_VERSION__}`;
+}
@@ -0,0 +1,7 @@
+import type { Money } from "./money";
+
+declare module "vitest" {
+	interface Assertion {
+		toBeMoney(expected: Money): void;
+	}
+}
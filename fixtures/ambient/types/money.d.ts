@@ -0,0 +1 @@
+export type Money = { amount: number; currency: string };
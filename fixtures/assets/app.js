@@ -0,0 +1,3 @@
+export const worker = new Worker(new URL("./worker.js", import.meta.url));
+export const wasm = new URL("./helper.wasm", import.meta.url);
+export const config = require.resolve("./config.json");
@@ -0,0 +1 @@
+import { worker } from "./app";
@@ -0,0 +1 @@
+import * as components from "./components";
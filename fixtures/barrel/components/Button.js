@@ -0,0 +1,3 @@
+import { cx } from "../utils/cx";
+
+export const Button = () => cx("button");
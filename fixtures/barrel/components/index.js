@@ -0,0 +1,3 @@
+export * from "./Button";
+export { Modal } from "./Modal";
+export { default as Icon } from "./Icon";
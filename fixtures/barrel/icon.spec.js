@@ -0,0 +1 @@
+import { Icon } from "./components";
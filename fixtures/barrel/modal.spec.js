@@ -0,0 +1 @@
+import { Modal } from "./components";
@@ -0,0 +1 @@
+export const cx = (name) => name;
@@ -0,0 +1,2 @@
+export const loadMessages = (lang) => import(`./locales/${lang}.json`);
+export const loadPage = (name) => require("./pages/" + name + ".js");
@@ -0,0 +1 @@
+import { loadMessages } from "./i18n";
@@ -0,0 +1,4 @@
+export const pages = import.meta.glob(["./pages/**/*.js", "!./pages/admin/**"]);
+export const messages = import.meta.glob("/fixtures/glob/locales/*.json", {
+  eager: true,
+});
@@ -0,0 +1 @@
+import { pages } from "./router";
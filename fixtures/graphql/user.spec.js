@@ -0,0 +1 @@
+import UserQuery from "./User.graphql";
@@ -0,0 +1 @@
+import ViewerQuery from "./Viewer.graphql";
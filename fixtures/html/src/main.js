@@ -0,0 +1 @@
+import { render } from "./app.js";
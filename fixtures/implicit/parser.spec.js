@@ -0,0 +1,6 @@
+test("parses every fixture", () => {
+  for (const name of ["select.sql", "insert.sql"]) {
+    const sql = fs.readFileSync(path.join(__dirname, "__fixtures__", name), "utf8");
+    expect(sql).toMatchSnapshot();
+  }
+});
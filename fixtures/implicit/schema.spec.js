@@ -0,0 +1,3 @@
+test("migrations apply cleanly", () => {
+  expect(true).toBe(true);
+});
@@ -0,0 +1,2 @@
+/** @type {import('./config').Config} */
+export const config = { name: "app" };
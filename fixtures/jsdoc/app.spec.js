@@ -0,0 +1 @@
+import { config } from "./app";
@@ -0,0 +1,6 @@
+/**
+ * @typedef {object} Config
+ * @property {string} name
+ */
+
+export {};
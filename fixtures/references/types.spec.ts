@@ -0,0 +1,6 @@
+/// <reference types="node" />
+/// <reference types="typed-lib" />
+import { version } from "./version";
+
+/// <reference types="not-in-the-header" />
+export const env = process.env[version];
@@ -0,0 +1 @@
+import { version } from "./version";
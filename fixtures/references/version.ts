@@ -0,0 +1,3 @@
+/// <reference path="./globals.d.ts" />
+
+export const version = __APP_VERSION__;
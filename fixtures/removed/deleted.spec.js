@@ -0,0 +1,3 @@
+import { helper } from './uses-deleted';
+
+helper();
@@ -0,0 +1,3 @@
+import { value } from './old-name';
+
+console.log(value);
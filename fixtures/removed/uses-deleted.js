@@ -0,0 +1 @@
+export { helper } from './deleted-helper';
@@ -0,0 +1,7 @@
+// `loadComponent` and `i18n` are set up as globals by the test environment.
+test("dashboard", async () => {
+  const Chart = await loadComponent("Chart");
+  const messages = await i18n.load("./locales/fr.json");
+  expect(Chart()).toBe("chart");
+  expect(messages.hello).toBe("bonjour");
+});
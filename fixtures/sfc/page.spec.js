@@ -0,0 +1 @@
+import Page from "./Page.astro";
@@ -0,0 +1 @@
+import "./card.less";
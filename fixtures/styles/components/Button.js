@@ -0,0 +1,3 @@
+import "./Button.scss";
+
+export const Button = () => null;
@@ -0,0 +1 @@
+import "./page.css";
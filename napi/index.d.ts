@@ -1,11 +1,161 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
+/** An error found while building the import graph. */
+export interface AffectedError {
+  /**
+   * * `'parse'`: the file has a syntax error.
+   * * `'unsupportedDynamicImport'`: an `import()` argument isn't a string literal.
+   * * `'invalidRequireArguments'`: a `require()` call doesn't have a single string literal argument,
+   *   or a require alias or import rule call's path argument isn't a string literal.
+   * * `'invalidGlobArguments'`: an `import.meta.glob()` pattern isn't a string literal starting
+   *   with `./`, `../` or `/`.
+   * * `'resolve'`: `specifier` couldn't be resolved.
+   * * `'unreadableFile'`: the file couldn't be read.
+   */
+  kind: 'parse' | 'unsupportedDynamicImport' | 'invalidRequireArguments' | 'invalidGlobArguments' | 'resolve' | 'unreadableFile'
+  /** The file with the error, relative to the current directory. */
+  file: string
+  message: string
+  specifier?: string
+  span?: ErrorSpan
+}
+
+/** One shortest import chain from an affected test file to a change. */
+export interface AffectedExplanation {
+  /** The affected test file, as passed in. */
+  file: string
+  /**
+   * Files from the test file down to the change, relative to the current
+   * directory. Starts with the test file itself.
+   */
+  chain: Array<string>
+  /**
+   * * `'fileChanged'`: the last file in the chain is in `changes`.
+   * * `'nodeModulesMatched'`: the last file in the chain belongs to a changed npm `package`.
+   * * `'npmFallbackMatched'`: the last file in the chain imports `specifier`, which couldn't be
+   *   resolved but matches a changed npm `package`.
+   * * `'removedFileImported'`: the last file in the chain imports `specifier`, which pointed
+   *   at the deleted or renamed file `path`.
+   * * `'implicitDependencyChanged'`: the last file in the chain depends on `path` through the
+   *   implicit dependency glob `pattern`, and `path` was added, deleted or renamed.
+   * * `'ambientDeclarationChanged'`: the last file in the chain is covered by the tsconfig, whose
+   *   ambient declaration file `path` changed. Only for `typeFiles`.
+   * * `'typesPackageChanged'`: the last file in the chain is covered by the tsconfig, which loads
+   *   the changed npm `package` through `compilerOptions.types`. Only for `typeFiles`.
+   */
+  reason: 'fileChanged' | 'nodeModulesMatched' | 'npmFallbackMatched' | 'removedFileImported' | 'implicitDependencyChanged' | 'ambientDeclarationChanged' | 'typesPackageChanged'
+  package?: string
+  specifier?: string
+  path?: string
+  pattern?: string
+}
+
 export interface AffectedResult {
   files: Array<string>
-  errors: Array<string>
+  /**
+   * Test files not in `files` whose type checking is affected by a change to an ambient
+   * declaration file or `compilerOptions.types` package of the tsconfig.
+   */
+  typeFiles: Array<string>
+  errors: Array<AffectedError>
+  /** Why each affected file was selected. Empty unless `explain` is set. */
+  explanations: Array<AffectedExplanation>
+}
+
+/** A byte range in a source file. */
+export interface ErrorSpan {
+  start: number
+  end: number
 }
 
-export declare function getAffected(testFiles: Array<string>, changes: Array<string>, resolveOptions: NapiResolveOptions, ignoreTypeImports?: boolean | undefined | null, requireAliases?: Array<string> | undefined | null): AffectedResult
+/**
+ * Returns the subset of `testFiles` that have any of `changes` in their import graph.
+ * Throws on invalid `changes` entries, `requireAliases` or `importRules`, and rethrows
+ * errors thrown by `resolveHook`.
+ */
+export declare function getAffected(testFiles: Array<string>, changes: Array<string>, resolveOptions: NapiResolveOptions, ignoreTypeImports?: boolean | undefined | null, requireAliases?: Array<string> | undefined | null, options?: NapiAffectedOptions | undefined | null): AffectedResult
+
+/**
+ * Lists the files changed in the git repository containing the current
+ * directory, as entries for `changes`.
+ *
+ * * `'since'`: files committed on `HEAD` since it forked from `base`.
+ * * `'sinceIncludingUncommitted'`: `'since'` plus everything not committed yet.
+ * * `'staged'`, `'unstaged'`, `'untracked'`: the respective working tree changes.
+ * * `'uncommitted'`: staged, unstaged and untracked changes together.
+ */
+export declare function getGitChanges(mode: 'since' | 'sinceIncludingUncommitted' | 'staged' | 'unstaged' | 'untracked' | 'uncommitted', base?: string | undefined | null): Array<string>
+
+/**
+ * Options for `getAffected` after `requireAliases`. Every option is off or empty by
+ * default.
+ */
+export interface NapiAffectedOptions {
+  /** Fill in `explanations`. */
+  explain?: boolean
+  /** Directory where the imports of every scanned file are stored between runs. */
+  cacheDir?: string
+  /** Follow changes per exported binding through re-exports. */
+  trackSymbols?: boolean
+  /**
+   * Make `import()` and `require()` calls with template literals or string concatenations
+   * depend on every file they could load.
+   */
+  expandDynamicImports?: boolean
+  /** Rules for collecting import specifiers from calls to custom loaders. */
+  importRules?: Array<NapiImportRule>
+  /**
+   * Called with the specifier and the importing file, and returns a path, `'ignore'`,
+   * `'builtin'`, or `undefined` to keep the resolver's outcome.
+   */
+  resolveHook?: (specifier: string, importer: string) => string | undefined | null
+  /**
+   * With `'fallback'`, the default, `resolveHook` is only called for imports the resolver
+   * can't resolve; with `'before'`, it's called for every import first.
+   */
+  resolveHookStage?: 'fallback' | 'before'
+  /** Files that tests depend on without importing them, like fixtures or snapshots. */
+  implicitDependencies?: Array<NapiImplicitDependency>
+}
+
+/** Files that `dependents` depend on without importing them, like fixtures or snapshots. */
+export interface NapiImplicitDependency {
+  /** Glob matching the dependent files, relative to the current directory. */
+  dependents: string
+  /**
+   * Glob matching the files each dependent depends on, relative to the current directory.
+   * `{dir}` is replaced with the dependent's directory and `{name}` with its file name.
+   */
+  dependencies: string
+}
+
+/**
+ * A rule for collecting import specifiers from calls to custom loaders, like
+ * `loadComponent('widgets/Chart')`.
+ */
+export interface NapiImportRule {
+  /**
+   * The called function, as a dotted member chain like `loadComponent` or `app.i18n.load`.
+   * A `*` segment matches any name.
+   */
+  callee: string
+  /** Index of the argument holding the specifier. Defaults to `0`. */
+  argument?: number
+  /**
+   * Dotted path of properties to read the specifier from when the argument is an object
+   * literal, like `component` for `route({ component: './Page' })`.
+   */
+  property?: string
+  /** Added in front of the matched string. */
+  prefix?: string
+  /** Added after the matched string. */
+  suffix?: string
+  /**
+   * The specifier is only referenced for its types, so it's skipped with
+   * `ignoreTypeImports`. Defaults to `false`.
+   */
+  typeOnly?: boolean
+}
 
 /**
  * Module Resolution Options
@@ -577,3 +577,4 @@ if (!nativeBinding) {
 
 module.exports = nativeBinding
 module.exports.getAffected = nativeBinding.getAffected
+module.exports.getGitChanges = nativeBinding.getGitChanges
@@ -3,11 +3,15 @@ extern crate napi_derive;
 extern crate oxc_resolver;
 extern crate sovra;
 
-use std::path::PathBuf;
+use std::{env, path::PathBuf, sync::mpsc, thread};
 
+use napi::bindgen_prelude::{FnArgs, Function};
 use napi_derive::napi;
 use oxc_resolver::{ResolveOptions, Resolver};
-use sovra::collect_affected;
+use sovra::{
+    git_changes, try_collect_affected_with_options, AffectedOptions, AffectedReason, GitChanges,
+    HookResolution, ImplicitDependency, ImportRule, ResolveHook, ResolveHookStage,
+};
 
 use self::options::{NapiResolveOptions, StrOrStrList};
 
@@ -16,9 +20,239 @@ mod options;
 #[napi(object)]
 pub struct AffectedResult {
     pub files: Vec<String>,
-    pub errors: Vec<String>,
+    /// Test files not in `files` whose type checking is affected by a change to an ambient
+    /// declaration file or `compilerOptions.types` package of the tsconfig.
+    pub type_files: Vec<String>,
+    pub errors: Vec<AffectedError>,
+    /// Why each affected file was selected. Empty unless `explain` is set.
+    pub explanations: Vec<AffectedExplanation>,
 }
 
+/// An error found while building the import graph.
+#[napi(object)]
+pub struct AffectedError {
+    /// * `'parse'`: the file has a syntax error.
+    /// * `'unsupportedDynamicImport'`: an `import()` argument isn't a string literal.
+    /// * `'invalidRequireArguments'`: a `require()` call doesn't have a single string literal argument,
+    ///   or a require alias or import rule call's path argument isn't a string literal.
+    /// * `'invalidGlobArguments'`: an `import.meta.glob()` pattern isn't a string literal starting
+    ///   with `./`, `../` or `/`.
+    /// * `'resolve'`: `specifier` couldn't be resolved.
+    /// * `'unreadableFile'`: the file couldn't be read.
+    #[napi(
+        ts_type = "'parse' | 'unsupportedDynamicImport' | 'invalidRequireArguments' | 'invalidGlobArguments' | 'resolve' | 'unreadableFile'"
+    )]
+    pub kind: String,
+    /// The file with the error, relative to the current directory.
+    pub file: String,
+    pub message: String,
+    pub specifier: Option<String>,
+    pub span: Option<ErrorSpan>,
+}
+
+/// A byte range in a source file.
+#[napi(object)]
+pub struct ErrorSpan {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl From<sovra::AffectedError> for AffectedError {
+    fn from(error: sovra::AffectedError) -> Self {
+        let kind = match error {
+            sovra::AffectedError::Parse { .. } => "parse",
+            sovra::AffectedError::UnsupportedDynamicImport { .. } => "unsupportedDynamicImport",
+            sovra::AffectedError::InvalidRequireArguments { .. } => "invalidRequireArguments",
+            sovra::AffectedError::InvalidGlobArguments { .. } => "invalidGlobArguments",
+            sovra::AffectedError::Resolve { .. } => "resolve",
+            sovra::AffectedError::UnreadableFile { .. } => "unreadableFile",
+        };
+        AffectedError {
+            kind: kind.to_string(),
+            file: error.file().to_string(),
+            message: error.message().to_string(),
+            specifier: error.specifier().map(str::to_string),
+            span: error.span().map(|span| ErrorSpan {
+                start: span.start,
+                end: span.end,
+            }),
+        }
+    }
+}
+
+/// One shortest import chain from an affected test file to a change.
+#[napi(object)]
+pub struct AffectedExplanation {
+    /// The affected test file, as passed in.
+    pub file: String,
+    /// Files from the test file down to the change, relative to the current
+    /// directory. Starts with the test file itself.
+    pub chain: Vec<String>,
+    /// * `'fileChanged'`: the last file in the chain is in `changes`.
+    /// * `'nodeModulesMatched'`: the last file in the chain belongs to a changed npm `package`.
+    /// * `'npmFallbackMatched'`: the last file in the chain imports `specifier`, which couldn't be
+    ///   resolved but matches a changed npm `package`.
+    /// * `'removedFileImported'`: the last file in the chain imports `specifier`, which pointed
+    ///   at the deleted or renamed file `path`.
+    /// * `'implicitDependencyChanged'`: the last file in the chain depends on `path` through the
+    ///   implicit dependency glob `pattern`, and `path` was added, deleted or renamed.
+    /// * `'ambientDeclarationChanged'`: the last file in the chain is covered by the tsconfig, whose
+    ///   ambient declaration file `path` changed. Only for `typeFiles`.
+    /// * `'typesPackageChanged'`: the last file in the chain is covered by the tsconfig, which loads
+    ///   the changed npm `package` through `compilerOptions.types`. Only for `typeFiles`.
+    #[napi(
+        ts_type = "'fileChanged' | 'nodeModulesMatched' | 'npmFallbackMatched' | 'removedFileImported' | 'implicitDependencyChanged' | 'ambientDeclarationChanged' | 'typesPackageChanged'"
+    )]
+    pub reason: String,
+    pub package: Option<String>,
+    pub specifier: Option<String>,
+    pub path: Option<String>,
+    pub pattern: Option<String>,
+}
+
+/// A rule for collecting import specifiers from calls to custom loaders, like
+/// `loadComponent('widgets/Chart')`.
+#[napi(object)]
+pub struct NapiImportRule {
+    /// The called function, as a dotted member chain like `loadComponent` or `app.i18n.load`.
+    /// A `*` segment matches any name.
+    pub callee: String,
+    /// Index of the argument holding the specifier. Defaults to `0`.
+    pub argument: Option<u32>,
+    /// Dotted path of properties to read the specifier from when the argument is an object
+    /// literal, like `component` for `route({ component: './Page' })`.
+    pub property: Option<String>,
+    /// Added in front of the matched string.
+    pub prefix: Option<String>,
+    /// Added after the matched string.
+    pub suffix: Option<String>,
+    /// The specifier is only referenced for its types, so it's skipped with
+    /// `ignoreTypeImports`. Defaults to `false`.
+    pub type_only: Option<bool>,
+}
+
+impl From<NapiImportRule> for ImportRule {
+    fn from(rule: NapiImportRule) -> Self {
+        ImportRule {
+            callee: rule.callee,
+            argument: rule.argument.unwrap_or(0) as usize,
+            property: rule.property,
+            prefix: rule.prefix,
+            suffix: rule.suffix,
+            type_only: rule.type_only.unwrap_or(false),
+        }
+    }
+}
+
+/// Files that `dependents` depend on without importing them, like fixtures or snapshots.
+#[napi(object)]
+pub struct NapiImplicitDependency {
+    /// Glob matching the dependent files, relative to the current directory.
+    pub dependents: String,
+    /// Glob matching the files each dependent depends on, relative to the current directory.
+    /// `{dir}` is replaced with the dependent's directory and `{name}` with its file name.
+    pub dependencies: String,
+}
+
+impl From<NapiImplicitDependency> for ImplicitDependency {
+    fn from(dependency: NapiImplicitDependency) -> Self {
+        ImplicitDependency {
+            dependents: dependency.dependents,
+            dependencies: dependency.dependencies,
+        }
+    }
+}
+
+/// Called with the specifier and the absolute path of the importing file.
+type ResolveHookFunction<'a> = Function<'a, FnArgs<(String, String)>, Option<String>>;
+
+/// A call to the resolve hook, with the channel to send its result on.
+type ResolveHookRequest = (String, PathBuf, mpsc::Sender<Option<HookResolution>>);
+
+/// Runs `collect` with a resolve hook that calls `callback`.
+///
+/// `getAffected` is synchronous, so the JS thread is blocked while the graph
+/// is scanned and a threadsafe function queued from the scanning threads
+/// would never run. Instead, `collect` runs on another thread and the hook
+/// sends its calls back to be made here, on the JS thread.
+fn with_resolve_hook<T: Send>(
+    callback: ResolveHookFunction<'_>,
+    stage: ResolveHookStage,
+    mut options: AffectedOptions,
+    collect: impl FnOnce(AffectedOptions) -> T + Send,
+) -> napi::Result<T> {
+    let (sender, requests) = mpsc::channel::<ResolveHookRequest>();
+    options.resolve_hook = Some(ResolveHook::new(stage, move |specifier, importer| {
+        let (reply, response) = mpsc::channel();
+        sender
+            .send((specifier.to_string(), importer.to_path_buf(), reply))
+            .ok()?;
+        response.recv().ok().flatten()
+    }));
+    thread::scope(|scope| {
+        let worker = scope.spawn(move || collect(options));
+        let mut error = None;
+        // Ends once `collect` has returned and dropped the hook.
+        for (specifier, importer, reply) in requests {
+            // After the first thrown error the remaining imports are left to the resolver.
+            if error.is_some() {
+                let _ = reply.send(None);
+                continue;
+            }
+            let args = (specifier, importer.to_string_lossy().into_owned());
+            let resolution = match callback.call(args.into()) {
+                Ok(result) => result.map(|result| match result.as_str() {
+                    "ignore" => HookResolution::Ignore,
+                    "builtin" => HookResolution::Builtin,
+                    _ => HookResolution::Path(PathBuf::from(result)),
+                }),
+                Err(e) => {
+                    error = Some(e);
+                    None
+                }
+            };
+            // The scanning thread waits for every reply.
+            let _ = reply.send(resolution);
+        }
+        let collected = worker.join().expect("collecting affected files panicked");
+        match error {
+            Some(error) => Err(error),
+            None => Ok(collected),
+        }
+    })
+}
+
+/// Options for `getAffected` after `requireAliases`. Every option is off or empty by
+/// default.
+#[napi(object, object_to_js = false)]
+#[derive(Default)]
+pub struct NapiAffectedOptions<'a> {
+    /// Fill in `explanations`.
+    pub explain: Option<bool>,
+    /// Directory where the imports of every scanned file are stored between runs.
+    pub cache_dir: Option<String>,
+    /// Follow changes per exported binding through re-exports.
+    pub track_symbols: Option<bool>,
+    /// Make `import()` and `require()` calls with template literals or string concatenations
+    /// depend on every file they could load.
+    pub expand_dynamic_imports: Option<bool>,
+    /// Rules for collecting import specifiers from calls to custom loaders.
+    pub import_rules: Option<Vec<NapiImportRule>>,
+    /// Called with the specifier and the importing file, and returns a path, `'ignore'`,
+    /// `'builtin'`, or `undefined` to keep the resolver's outcome.
+    #[napi(ts_type = "(specifier: string, importer: string) => string | undefined | null")]
+    pub resolve_hook: Option<ResolveHookFunction<'a>>,
+    /// With `'fallback'`, the default, `resolveHook` is only called for imports the resolver
+    /// can't resolve; with `'before'`, it's called for every import first.
+    #[napi(ts_type = "'fallback' | 'before'")]
+    pub resolve_hook_stage: Option<String>,
+    /// Files that tests depend on without importing them, like fixtures or snapshots.
+    pub implicit_dependencies: Option<Vec<NapiImplicitDependency>>,
+}
+
+/// Returns the subset of `testFiles` that have any of `changes` in their import graph.
+/// Throws on invalid `changes` entries, `requireAliases` or `importRules`, and rethrows
+/// errors thrown by `resolveHook`.
 #[allow(clippy::needless_pass_by_value)]
 #[napi]
 pub fn get_affected(
@@ -27,20 +261,157 @@ pub fn get_affected(
     resolve_options: NapiResolveOptions,
     ignore_type_imports: Option<bool>,
     require_aliases: Option<Vec<String>>,
-) -> AffectedResult {
+    options: Option<NapiAffectedOptions<'_>>,
+) -> napi::Result<AffectedResult> {
     let resolver = Resolver::new(normalize_options(resolve_options));
-    let require_aliases = require_aliases.unwrap_or_default();
-    let affected = collect_affected(
-        test_files.iter().map(AsRef::as_ref).collect(),
-        changes.iter().map(AsRef::as_ref).collect(),
-        resolver,
-        ignore_type_imports.unwrap_or(false),
-        require_aliases.iter().map(AsRef::as_ref).collect(),
-    );
-    AffectedResult {
-        files: affected.files,
-        errors: affected.errors,
+    let NapiAffectedOptions {
+        explain,
+        cache_dir,
+        track_symbols,
+        expand_dynamic_imports,
+        import_rules,
+        resolve_hook,
+        resolve_hook_stage,
+        implicit_dependencies,
+    } = options.unwrap_or_default();
+    let options = AffectedOptions {
+        ignore_type_imports: ignore_type_imports.unwrap_or(false),
+        require_aliases: require_aliases.unwrap_or_default(),
+        import_rules: import_rules
+            .unwrap_or_default()
+            .into_iter()
+            .map(ImportRule::from)
+            .collect(),
+        explain: explain.unwrap_or(false),
+        cache_dir: cache_dir.map(PathBuf::from),
+        track_symbols: track_symbols.unwrap_or(false),
+        expand_dynamic_imports: expand_dynamic_imports.unwrap_or(false),
+        resolve_hook: None,
+        implicit_dependencies: implicit_dependencies
+            .unwrap_or_default()
+            .into_iter()
+            .map(ImplicitDependency::from)
+            .collect(),
+    };
+    let stage = match resolve_hook_stage.as_deref() {
+        None | Some("fallback") => ResolveHookStage::Fallback,
+        Some("before") => ResolveHookStage::Before,
+        Some(stage) => {
+            return Err(napi::Error::from_reason(format!(
+                "Unknown resolve hook stage '{stage}'"
+            )))
+        }
+    };
+    // Invalid input throws instead of panicking, which would abort the
+    // whole Node process.
+    let collect = |options: AffectedOptions| {
+        try_collect_affected_with_options(
+            test_files.iter().map(AsRef::as_ref).collect(),
+            changes.iter().map(AsRef::as_ref).collect(),
+            resolver,
+            &options,
+        )
+    };
+    let affected = match resolve_hook {
+        Some(callback) => with_resolve_hook(callback, stage, options, collect)?,
+        None => collect(options),
     }
+    .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e.to_string()))?;
+    Ok(AffectedResult {
+        files: affected.files,
+        type_files: affected.type_files,
+        errors: affected
+            .errors
+            .into_iter()
+            .map(AffectedError::from)
+            .collect(),
+        explanations: affected
+            .explanations
+            .into_iter()
+            .map(|explanation| {
+                let (reason, package, specifier, path, pattern) = match explanation.reason {
+                    AffectedReason::FileChanged => ("fileChanged", None, None, None, None),
+                    AffectedReason::NodeModulesMatched { package } => {
+                        ("nodeModulesMatched", Some(package), None, None, None)
+                    }
+                    AffectedReason::NpmFallbackMatched { specifier, package } => (
+                        "npmFallbackMatched",
+                        Some(package),
+                        Some(specifier),
+                        None,
+                        None,
+                    ),
+                    AffectedReason::RemovedFileImported { specifier, path } => (
+                        "removedFileImported",
+                        None,
+                        Some(specifier),
+                        Some(path),
+                        None,
+                    ),
+                    AffectedReason::ImplicitDependencyChanged { pattern, path } => (
+                        "implicitDependencyChanged",
+                        None,
+                        None,
+                        Some(path),
+                        Some(pattern),
+                    ),
+                    AffectedReason::AmbientDeclarationChanged { path } => {
+                        ("ambientDeclarationChanged", None, None, Some(path), None)
+                    }
+                    AffectedReason::TypesPackageChanged { package } => {
+                        ("typesPackageChanged", Some(package), None, None, None)
+                    }
+                };
+                AffectedExplanation {
+                    file: explanation.file,
+                    chain: explanation.chain,
+                    reason: reason.to_string(),
+                    package,
+                    specifier,
+                    path,
+                    pattern,
+                }
+            })
+            .collect(),
+    })
+}
+
+/// Lists the files changed in the git repository containing the current
+/// directory, as entries for `changes`.
+///
+/// * `'since'`: files committed on `HEAD` since it forked from `base`.
+/// * `'sinceIncludingUncommitted'`: `'since'` plus everything not committed yet.
+/// * `'staged'`, `'unstaged'`, `'untracked'`: the respective working tree changes.
+/// * `'uncommitted'`: staged, unstaged and untracked changes together.
+#[napi]
+pub fn get_git_changes(
+    #[napi(
+        ts_arg_type = "'since' | 'sinceIncludingUncommitted' | 'staged' | 'unstaged' | 'untracked' | 'uncommitted'"
+    )]
+    mode: String,
+    base: Option<String>,
+) -> napi::Result<Vec<String>> {
+    let require_base = || {
+        base.clone().ok_or_else(|| {
+            napi::Error::from_reason(format!("A base ref is required for mode '{mode}'"))
+        })
+    };
+    let changes = match mode.as_str() {
+        "since" => GitChanges::Since(require_base()?),
+        "sinceIncludingUncommitted" => GitChanges::SinceIncludingUncommitted(require_base()?),
+        "staged" => GitChanges::Staged,
+        "unstaged" => GitChanges::Unstaged,
+        "untracked" => GitChanges::Untracked,
+        "uncommitted" => GitChanges::Uncommitted,
+        _ => return Err(napi::Error::from_reason(format!("Unknown mode '{mode}'"))),
+    };
+    let current_dir = env::current_dir()?;
+    let entries =
+        git_changes(&current_dir, &changes).map_err(|e| napi::Error::from_reason(e.to_string()))?;
+    Ok(entries
+        .iter()
+        .map(|entry| entry.format(&current_dir))
+        .collect())
 }
 
 fn normalize_options(op: NapiResolveOptions) -> ResolveOptions {
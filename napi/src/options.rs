@@ -191,9 +191,9 @@ impl From<TsconfigOptions> for oxc_resolver::TsconfigDiscovery {
 type StrOrStrListType = Either<String, Vec<String>>;
 pub struct StrOrStrList(pub StrOrStrListType);
 
-impl Into<Vec<String>> for StrOrStrList {
-    fn into(self) -> Vec<String> {
-        match self {
+impl From<StrOrStrList> for Vec<String> {
+    fn from(value: StrOrStrList) -> Self {
+        match value {
             StrOrStrList(Either::A(s)) => Vec::from([s]),
             StrOrStrList(Either::B(a)) => a,
         }
@@ -55,3 +55,220 @@ const typeImportIgnored = getAffected(
 );
 assert.deepEqual(typeImportIgnored.errors, []);
 assert.deepEqual(typeImportIgnored.files, []);
+
+const explained = getAffected(
+  typeImportTestFiles,
+  typeImportChanges,
+  tsResolverOptions,
+  false,
+  [],
+  { explain: true }
+);
+assert.deepEqual(explained.errors, []);
+assert.deepEqual(explained.files, typeImportTestFiles);
+assert.equal(explained.explanations.length, 1);
+assert.equal(explained.explanations[0].file, typeImportTestFiles[0]);
+assert.equal(explained.explanations[0].reason, "fileChanged");
+
+const rulesFixturesPath = resolve(
+  fileURLToPath(import.meta.url),
+  "../../../fixtures/rules"
+);
+const rulesAffected = getAffected(
+  [join(rulesFixturesPath, "dashboard.spec.js")],
+  [join(rulesFixturesPath, "widgets/Chart.js")],
+  resolverOptions,
+  false,
+  [],
+  {
+    importRules: [
+      { callee: "loadComponent", prefix: "./widgets/", suffix: ".js" },
+      { callee: "i18n.load" },
+    ],
+  }
+);
+assert.deepEqual(rulesAffected.errors, []);
+assert.deepEqual(rulesAffected.files, [
+  join(rulesFixturesPath, "dashboard.spec.js"),
+]);
+
+const ambientFixturesPath = resolve(
+  fileURLToPath(import.meta.url),
+  "../../../fixtures/ambient"
+);
+const ambientAffected = getAffected(
+  [join(ambientFixturesPath, "format.spec.ts")],
+  [join(ambientFixturesPath, "types/global.d.ts")],
+  {
+    extensions: [".ts"],
+    tsconfig: {
+      configFile: join(ambientFixturesPath, "tsconfig.json"),
+      references: "auto",
+    },
+  }
+);
+assert.deepEqual(ambientAffected.errors, []);
+assert.deepEqual(ambientAffected.files, []);
+assert.deepEqual(ambientAffected.typeFiles, [
+  join(ambientFixturesPath, "format.spec.ts"),
+]);
+
+const badImportFile = resolve(
+  fileURLToPath(import.meta.url),
+  "../../../fixtures/bad-import.js"
+);
+const badImport = getAffected([badImportFile], [], resolverOptions);
+assert.equal(badImport.errors.length, 1);
+assert.equal(badImport.errors[0].kind, "resolve");
+assert.equal(badImport.errors[0].specifier, "bad-import");
+
+/** @type {Array<[string, string]>} */
+const hookCalls = [];
+const hooked = getAffected(
+  [badImportFile],
+  [join(fixturesPath, "module.js")],
+  resolverOptions,
+  false,
+  [],
+  {
+    resolveHook: (specifier, importer) => {
+      hookCalls.push([specifier, importer]);
+      return specifier === "bad-import"
+        ? join(fixturesPath, "module.js")
+        : undefined;
+    },
+  }
+);
+assert.deepEqual(hooked.errors, []);
+assert.deepEqual(hooked.files, [badImportFile]);
+assert.deepEqual(hookCalls, [["bad-import", badImportFile]]);
+
+const ignored = getAffected(
+  [badImportFile],
+  [],
+  resolverOptions,
+  false,
+  [],
+  { resolveHook: () => "ignore", resolveHookStage: "before" }
+);
+assert.deepEqual(ignored.errors, []);
+
+let throwingCalls = 0;
+assert.throws(
+  () =>
+    getAffected(
+      [join(fixturesPath, "all.js")],
+      [],
+      resolverOptions,
+      false,
+      [],
+      {
+        resolveHook: () => {
+          throwingCalls += 1;
+          throw new Error("hook failed");
+        },
+        resolveHookStage: "before",
+      }
+    ),
+  /hook failed/
+);
+// The hook isn't called again after it throws.
+assert.equal(throwingCalls, 1);
+
+// Implicit dependency globs are relative to the current directory.
+const implicitFixturesPath = resolve(
+  fileURLToPath(import.meta.url),
+  "../../../fixtures/implicit"
+);
+const previousDir = process.cwd();
+process.chdir(resolve(implicitFixturesPath, "../.."));
+const implicitOptions = {
+  implicitDependencies: [
+    {
+      dependents: "fixtures/**/*.spec.js",
+      dependencies: "{dir}/__snapshots__/{name}.snap",
+    },
+  ],
+};
+const implicitAffected = getAffected(
+  [join(implicitFixturesPath, "parser.spec.js")],
+  [join(implicitFixturesPath, "__snapshots__/parser.spec.js.snap")],
+  resolverOptions,
+  false,
+  [],
+  implicitOptions
+);
+const implicitAdded = getAffected(
+  [join(implicitFixturesPath, "schema.spec.js")],
+  ["added:fixtures/implicit/__snapshots__/schema.spec.js.snap"],
+  resolverOptions,
+  false,
+  [],
+  { ...implicitOptions, explain: true }
+);
+process.chdir(previousDir);
+assert.deepEqual(implicitAffected.errors, []);
+assert.deepEqual(implicitAffected.files, [
+  join(implicitFixturesPath, "parser.spec.js"),
+]);
+assert.equal(implicitAdded.explanations[0].reason, "implicitDependencyChanged");
+assert.equal(
+  implicitAdded.explanations[0].pattern,
+  "fixtures/implicit/__snapshots__/schema.spec.js.snap"
+);
+
+assert.throws(
+  () => getAffected(testFiles, ["npm:"], resolverOptions),
+  /Invalid changeset entry 'npm:'/
+);
+assert.throws(
+  () => getAffected(testFiles, changes, resolverOptions, false, ["a.b.c"]),
+  /Invalid require alias 'a.b.c'/
+);
+
+const barrelPath = resolve(
+  fileURLToPath(import.meta.url),
+  "../../../fixtures/barrel"
+);
+const barrelTestFiles = [
+  join(barrelPath, "button.spec.js"),
+  join(barrelPath, "modal.spec.js"),
+];
+const barrelChanges = [join(barrelPath, "components/Modal.js")];
+assert.deepEqual(
+  getAffected(barrelTestFiles, barrelChanges, resolverOptions).files.sort(),
+  barrelTestFiles
+);
+assert.deepEqual(
+  getAffected(
+    barrelTestFiles,
+    barrelChanges,
+    resolverOptions,
+    false,
+    [],
+    { trackSymbols: true }
+  ).files,
+  [join(barrelPath, "modal.spec.js")]
+);
+
+const dynamicImportPath = resolve(
+  fileURLToPath(import.meta.url),
+  "../../../fixtures/dynamic-import"
+);
+const i18nTestFiles = [join(dynamicImportPath, "i18n.spec.js")];
+const i18nChanges = [join(dynamicImportPath, "locales/sv.json")];
+assert.deepEqual(
+  getAffected(i18nTestFiles, i18nChanges, resolverOptions).files,
+  []
+);
+assert.deepEqual(
+  getAffected(
+    i18nTestFiles,
+    i18nChanges,
+    resolverOptions,
+    false,
+    [],
+    { expandDynamicImports: true }
+  ).files,
+  i18nTestFiles
+);
@@ -1,288 +1,168 @@
-use std::{
-    collections::{HashMap, HashSet},
-    env, fs,
-    path::{Path, PathBuf},
-};
-
-use oxc_resolver::{ResolveError, Resolver};
-use oxc_span::SourceType;
-use rayon::prelude::*;
-
-use crate::changeset::{
-    bare_specifier_segments, matches_changed_package, node_modules_segments, parse_changed_entry,
-    ChangedEntry,
-};
-use crate::imports::{self, RequireAlias};
+use std::path::PathBuf;
+
+use oxc_resolver::Resolver;
+
+use crate::changeset::parse_changed_entry;
+use crate::error::{current_dir, AffectedError, InputError};
+use crate::graph::DependencyGraph;
+use crate::hook::ResolveHook;
+use crate::implicit::ImplicitDependency;
+use crate::rules::ImportRule;
 
 pub struct AffectedReturn {
-    pub errors: Vec<String>,
+    pub errors: Vec<AffectedError>,
     pub files: Vec<String>,
+    /// Test files not in `files` whose type checking is affected, because
+    /// the changes touch an ambient declaration file or `types` package of
+    /// a tsconfig. See [`AffectedReason::AmbientDeclarationChanged`].
+    pub type_files: Vec<String>,
+    /// Why each affected file was selected; only filled in when
+    /// [`AffectedOptions::explain`] is set.
+    pub explanations: Vec<Explanation>,
 }
 
-fn extend_affected(
-    affected: &mut HashSet<PathBuf>,
-    import: &PathBuf,
-    dependents_map: &HashMap<PathBuf, HashSet<PathBuf>>,
-) {
-    affected.insert(import.clone());
-    match dependents_map.get(import) {
-        None => return,
-        Some(dependents) => {
-            for dependent in dependents.iter() {
-                if affected.contains(dependent) {
-                    continue;
-                }
-                extend_affected(affected, dependent, dependents_map);
-            }
-        }
-    }
+/// Options for [`collect_affected_with_options`] and [`DependencyGraph`].
+#[derive(Debug, Clone, Default)]
+pub struct AffectedOptions {
+    /// Exclude type-only imports (`import type`) from the import graph.
+    pub ignore_type_imports: bool,
+    /// Calls to collect like `require()`, each `"name"` or `"object.method"`.
+    pub require_aliases: Vec<String>,
+    /// Rules for collecting specifiers from calls to custom loaders.
+    pub import_rules: Vec<ImportRule>,
+    /// Record an [`Explanation`] for every affected file.
+    pub explain: bool,
+    /// Directory for persisting per-file scan results between runs. Files
+    /// whose content is unchanged are not parsed again.
+    pub cache_dir: Option<PathBuf>,
+    /// Follow changes per exported symbol through re-exports, so importing
+    /// one binding from a barrel file isn't affected by changes to the
+    /// modules behind the barrel's other exports.
+    pub track_symbols: bool,
+    /// Expand `import()` and `require()` calls whose argument is a template
+    /// literal or concatenation with a static relative prefix, like
+    /// `./locales/${lang}.json`, to every file they could refer to.
+    pub expand_dynamic_imports: bool,
+    /// Custom resolution for imports the resolver can't handle on its own.
+    pub resolve_hook: Option<ResolveHook>,
+    /// Files that tests or modules depend on without importing them, like
+    /// fixtures and golden files.
+    pub implicit_dependencies: Vec<ImplicitDependency>,
 }
 
-enum ScanEdge {
-    /// Resolved to a regular file; `is_in_node_modules` is precomputed to
-    /// avoid touching `module_paths` again during the (sequential) merge.
-    Resolved {
-        import: PathBuf,
-        is_in_node_modules: bool,
-    },
-    /// Resolve failed but the bare specifier matched a changed npm
-    /// package — the importing file should be marked affected.
-    NpmFallbackMatched,
-    /// Resolve failed and didn't match any changeset entry; surface it.
-    UnresolvedError(String),
+/// The kind of change at the end of an [`Explanation::chain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AffectedReason {
+    /// The last file in the chain is in the changeset.
+    FileChanged,
+    /// The last file in the chain resolved into a module folder belonging to
+    /// the changed npm `package`.
+    NodeModulesMatched { package: String },
+    /// The last file in the chain imports `specifier`, which couldn't be
+    /// resolved but matches the changed npm `package`.
+    NpmFallbackMatched { specifier: String, package: String },
+    /// The last file in the chain imports `specifier`, which no longer
+    /// resolves because the changeset deleted or renamed `path`.
+    RemovedFileImported { specifier: String, path: String },
+    /// The last file in the chain depends on `path` through the implicit
+    /// dependency glob `pattern`, and the changeset added, deleted or renamed
+    /// `path`.
+    ImplicitDependencyChanged { pattern: String, path: String },
+    /// The last file in the chain is covered by a tsconfig, whose ambient
+    /// declaration file `path` changed. Only used for
+    /// [`AffectedReturn::type_files`].
+    AmbientDeclarationChanged { path: String },
+    /// The last file in the chain is covered by a tsconfig, which loads the
+    /// changed npm `package` through `compilerOptions.types`.
+    /// Only used for [`AffectedReturn::type_files`].
+    TypesPackageChanged { package: String },
 }
 
-struct FileScan {
-    absolute_path: PathBuf,
-    parser_errors: Vec<String>,
-    edges: Vec<ScanEdge>,
+/// One shortest import chain from an affected test file to a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// The affected test file, as passed in.
+    pub file: String,
+    /// Files from the test file down to the change, relative to the current
+    /// directory. Starts with the test file itself.
+    pub chain: Vec<String>,
+    pub reason: AffectedReason,
 }
 
-fn scan_file(
-    absolute_path: PathBuf,
-    resolver: &Resolver,
-    current_dir: &Path,
-    module_paths: &HashSet<&str>,
-    changed_packages: &HashSet<String>,
+/// Returns the subset of `test_files` that have any of `changes` in their
+/// import graph. Use [`DependencyGraph`] directly to query the same test files
+/// with several changesets.
+///
+/// Panics on invalid `changes` entries or `require_aliases`; use
+/// [`try_collect_affected`] to handle those as errors.
+pub fn collect_affected(
+    test_files: Vec<&str>,
+    changes: Vec<&str>,
+    resolver: Resolver,
     ignore_type_imports: bool,
-    require_aliases: &[RequireAlias],
-) -> FileScan {
-    let mut parser_errors = Vec::new();
-    let mut edges = Vec::new();
-
-    let Ok(source_type) = SourceType::from_path(absolute_path.clone()) else {
-        return FileScan {
-            absolute_path,
-            parser_errors,
-            edges,
-        };
-    };
-    let Ok(source_text) = fs::read_to_string(&absolute_path) else {
-        parser_errors.push(format!("Cannot read file: {absolute_path:?}"));
-        return FileScan {
-            absolute_path,
-            parser_errors,
-            edges,
-        };
-    };
-
-    let result = imports::collect_imports(
-        source_type,
-        source_text.as_str(),
-        Some(&absolute_path),
+    require_aliases: Vec<&str>,
+) -> AffectedReturn {
+    let options = AffectedOptions {
         ignore_type_imports,
-        require_aliases,
-    );
-    parser_errors.extend(result.errors);
-
-    let Some(parent_path) = absolute_path.parent() else {
-        return FileScan {
-            absolute_path,
-            parser_errors,
-            edges,
-        };
+        require_aliases: require_aliases.iter().map(|s| s.to_string()).collect(),
+        ..AffectedOptions::default()
     };
-
-    edges.reserve(result.imports_paths.len());
-    for import_path in result.imports_paths.iter() {
-        match resolver.resolve(parent_path, import_path.as_str()) {
-            Err(ResolveError::Builtin { .. }) => {} // Skip builtins
-            Err(e) => {
-                // Fallback: if the resolver couldn't find the module on
-                // disk (e.g. `node_modules` not installed), match the raw
-                // specifier against the npm changeset.
-                let matched = !changed_packages.is_empty()
-                    && bare_specifier_segments(import_path.as_str())
-                        .is_some_and(|s| matches_changed_package(&s, changed_packages));
-                if matched {
-                    edges.push(ScanEdge::NpmFallbackMatched);
-                } else {
-                    let relative_path = absolute_path
-                        .strip_prefix(current_dir)
-                        .unwrap_or(&absolute_path)
-                        .to_str()
-                        .unwrap_or("unknown file");
-                    edges.push(ScanEdge::UnresolvedError(format!("[{relative_path}]\n{e}")));
-                }
-            }
-            Ok(resolution) => {
-                let import = current_dir.join(resolution.path());
-                let is_in_node_modules = import
-                    .components()
-                    .any(|c| module_paths.contains(c.to_owned().as_os_str().to_str().unwrap()));
-                edges.push(ScanEdge::Resolved {
-                    import,
-                    is_in_node_modules,
-                });
-            }
-        }
-    }
-    FileScan {
-        absolute_path,
-        parser_errors,
-        edges,
-    }
+    collect_affected_with_options(test_files, changes, resolver, &options)
 }
 
-pub fn collect_affected(
+/// Like [`collect_affected`], but returns an [`InputError`] instead of
+/// panicking on invalid input.
+pub fn try_collect_affected(
     test_files: Vec<&str>,
     changes: Vec<&str>,
     resolver: Resolver,
     ignore_type_imports: bool,
     require_aliases: Vec<&str>,
-) -> AffectedReturn {
-    let require_aliases: Vec<RequireAlias> = require_aliases
-        .iter()
-        .map(|s| RequireAlias::parse(s))
-        .collect();
-    let current_dir = env::current_dir().unwrap();
-    let module_paths: HashSet<&str> =
-        HashSet::from_iter(resolver.options().modules.iter().map(|m| m.as_str()));
-
-    let mut affected: HashSet<PathBuf> = HashSet::new();
-    let mut changed_packages: HashSet<String> = HashSet::new();
-    for entry in changes {
-        match parse_changed_entry(entry, &current_dir) {
-            ChangedEntry::File(p) => {
-                affected.insert(p);
-            }
-            ChangedEntry::Package(name) => {
-                changed_packages.insert(name);
-            }
-        }
-    }
-    let mut errors: Vec<String> = Vec::new();
-
-    let test_files_path_map: HashMap<&str, PathBuf> = HashMap::from_iter(
-        test_files
-            .iter()
-            .map(|p: &&str| (*p, current_dir.join(p).to_path_buf()))
-            .collect::<Vec<_>>(),
-    );
-    let mut frontier: Vec<PathBuf> = test_files_path_map
-        .values()
-        .cloned()
-        .filter(|p| !affected.contains(p))
-        .collect();
-    let mut dependents_map: HashMap<PathBuf, HashSet<PathBuf>> = HashMap::new();
-
-    while !frontier.is_empty() {
-        let mut to_scan: Vec<PathBuf> = Vec::with_capacity(frontier.len());
-        for path in frontier.drain(..) {
-            if affected.contains(&path) {
-                extend_affected(&mut affected, &path, &dependents_map);
-                continue;
-            }
-            to_scan.push(path);
-        }
-
-        let scans: Vec<FileScan> = to_scan
-            .into_par_iter()
-            .map(|path| {
-                scan_file(
-                    path,
-                    &resolver,
-                    &current_dir,
-                    &module_paths,
-                    &changed_packages,
-                    ignore_type_imports,
-                    &require_aliases,
-                )
-            })
-            .collect();
-
-        let mut next_frontier: Vec<PathBuf> = Vec::new();
-        for scan in scans {
-            errors.extend(scan.parser_errors);
-            let absolute_path = scan.absolute_path;
-            for edge in scan.edges {
-                match edge {
-                    ScanEdge::UnresolvedError(e) => errors.push(e),
-                    ScanEdge::NpmFallbackMatched => {
-                        extend_affected(&mut affected, &absolute_path, &dependents_map);
-                    }
-                    ScanEdge::Resolved {
-                        import,
-                        is_in_node_modules,
-                    } => {
-                        // First time we see a node_modules path, check if
-                        // any segment-prefix of the package matches a
-                        // changed entry.
-                        if is_in_node_modules
-                            && !changed_packages.is_empty()
-                            && !affected.contains(&import)
-                            && !dependents_map.contains_key(&import)
-                        {
-                            if let Some(segments) = node_modules_segments(&import, &module_paths) {
-                                if matches_changed_package(&segments, &changed_packages) {
-                                    affected.insert(import.clone());
-                                }
-                            }
-                        }
-
-                        if affected.contains(&import) {
-                            extend_affected(&mut affected, &absolute_path, &dependents_map);
-                        } else if dependents_map.contains_key(&import) {
-                            if let Some(dependents) = dependents_map.get_mut(&import) {
-                                dependents.insert(absolute_path.clone());
-                            }
-                        } else {
-                            dependents_map.insert(
-                                import.clone(),
-                                HashSet::from_iter(vec![absolute_path.clone()]),
-                            );
-
-                            // Skip node_modules
-                            if is_in_node_modules {
-                                continue;
-                            }
-                            next_frontier.push(import);
-                        }
-                    }
-                }
-            }
-        }
+) -> Result<AffectedReturn, InputError> {
+    let options = AffectedOptions {
+        ignore_type_imports,
+        require_aliases: require_aliases.iter().map(|s| s.to_string()).collect(),
+        ..AffectedOptions::default()
+    };
+    try_collect_affected_with_options(test_files, changes, resolver, &options)
+}
 
-        frontier = next_frontier;
-    }
+/// Like [`collect_affected`], configured through [`AffectedOptions`].
+pub fn collect_affected_with_options(
+    test_files: Vec<&str>,
+    changes: Vec<&str>,
+    resolver: Resolver,
+    options: &AffectedOptions,
+) -> AffectedReturn {
+    try_collect_affected_with_options(test_files, changes, resolver, options)
+        .unwrap_or_else(|e| panic!("{e}"))
+}
 
-    AffectedReturn {
-        errors,
-        files: test_files_path_map
-            .iter()
-            .filter(|(_f, p)| affected.contains(*p))
-            .map(|(f, _)| f.to_string())
-            .collect(),
-    }
+/// Like [`collect_affected_with_options`], but returns an [`InputError`]
+/// instead of panicking on invalid input.
+pub fn try_collect_affected_with_options(
+    test_files: Vec<&str>,
+    changes: Vec<&str>,
+    resolver: Resolver,
+    options: &AffectedOptions,
+) -> Result<AffectedReturn, InputError> {
+    // Validate the changes before scanning the whole graph.
+    let current_dir = current_dir()?;
+    let changes = changes
+        .into_iter()
+        .map(|entry| parse_changed_entry(entry, &current_dir))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(DependencyGraph::try_new(test_files, resolver, options)?.affected_by(changes))
 }
 
 #[cfg(test)]
 mod tests {
-    use std::vec;
+    use std::{collections::HashSet, env, vec};
 
     use oxc_resolver::{ResolveOptions, TsconfigDiscovery, TsconfigOptions, TsconfigReferences};
 
     use super::*;
+    use crate::hook::{HookResolution, ResolveHookStage};
 
     fn assert_collect_affected(
         test_files: Vec<&str>,
@@ -303,11 +183,23 @@ mod tests {
         let ret = collect_affected(test_files, changes, resolver, ignore_type_imports, vec![]);
         let expected: HashSet<String> = HashSet::from_iter(expected.iter().map(|s| s.to_string()));
         let actual: HashSet<String> = HashSet::from_iter(ret.files.iter().map(|s| s.to_string()));
-        let no_errors: Vec<String> = vec![];
+        let no_errors: Vec<AffectedError> = vec![];
         assert_eq!(expected, actual);
         assert_eq!(ret.errors, no_errors);
     }
 
+    /// Collects the affected files, asserting that there were no errors.
+    fn collect_affected_ok(
+        test_files: Vec<&str>,
+        changes: Vec<&str>,
+        resolver: Resolver,
+        options: &AffectedOptions,
+    ) -> AffectedReturn {
+        let ret = collect_affected_with_options(test_files, changes, resolver, options);
+        assert!(ret.errors.is_empty(), "unexpected errors: {:?}", ret.errors);
+        ret
+    }
+
     fn assert_affected(test_files: Vec<&str>, changes: Vec<&str>) {
         assert_collect_affected(
             test_files.clone(),
@@ -339,6 +231,19 @@ mod tests {
         })
     }
 
+    fn ambient_resolver() -> Resolver {
+        Resolver::new(ResolveOptions {
+            extensions: vec![".ts".into()],
+            tsconfig: Some(TsconfigDiscovery::Manual(TsconfigOptions {
+                config_file: env::current_dir()
+                    .unwrap()
+                    .join("fixtures/ambient/tsconfig.json"),
+                references: TsconfigReferences::Auto,
+            })),
+            ..ResolveOptions::default()
+        })
+    }
+
     #[test]
     fn test_require() {
         assert_affected(
@@ -348,7 +253,6 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn test_require_context() {
         assert_affected(
             vec!["fixtures/require/context.js"],
@@ -356,6 +260,449 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_import_meta_glob() {
+        let test_files = vec!["fixtures/glob/router.spec.js"];
+        assert_affected(test_files.clone(), vec!["fixtures/glob/pages/home.js"]);
+        assert_affected(test_files.clone(), vec!["fixtures/glob/locales/sv.json"]);
+        assert_unaffected(test_files, vec!["fixtures/glob/pages/admin/users.js"]);
+    }
+
+    #[test]
+    fn test_import_rules() {
+        let options = AffectedOptions {
+            import_rules: vec![
+                ImportRule {
+                    callee: "loadComponent".to_string(),
+                    prefix: Some("./widgets/".to_string()),
+                    suffix: Some(".js".to_string()),
+                    ..ImportRule::default()
+                },
+                ImportRule {
+                    callee: "i18n.load".to_string(),
+                    ..ImportRule::default()
+                },
+            ],
+            ..AffectedOptions::default()
+        };
+        for change in [
+            "fixtures/rules/widgets/Chart.js",
+            "fixtures/rules/locales/fr.json",
+        ] {
+            let ret = collect_affected_ok(
+                vec!["fixtures/rules/dashboard.spec.js"],
+                vec![change],
+                Resolver::new(ResolveOptions::default()),
+                &options,
+            );
+            assert_eq!(ret.files, vec!["fixtures/rules/dashboard.spec.js"]);
+        }
+    }
+
+    fn collect_with_hook(
+        test_file: &str,
+        change: &str,
+        stage: ResolveHookStage,
+        resolution: HookResolution,
+    ) -> AffectedReturn {
+        let options = AffectedOptions {
+            resolve_hook: Some(ResolveHook::new(stage, move |specifier, importer| {
+                assert!(importer.is_absolute());
+                (specifier == "bad-import" || specifier == "./module").then(|| resolution.clone())
+            })),
+            ..AffectedOptions::default()
+        };
+        collect_affected_with_options(
+            vec![test_file],
+            vec![change],
+            Resolver::new(ResolveOptions::default()),
+            &options,
+        )
+    }
+
+    #[test]
+    fn test_resolve_hook_fallback() {
+        let ret = collect_with_hook(
+            "fixtures/bad-import.js",
+            "fixtures/nested/module.js",
+            ResolveHookStage::Fallback,
+            HookResolution::Path(PathBuf::from("fixtures/nested/module.js")),
+        );
+        assert!(ret.errors.is_empty());
+        assert_eq!(ret.files, vec!["fixtures/bad-import.js"]);
+
+        for resolution in [HookResolution::Ignore, HookResolution::Builtin] {
+            let ret = collect_with_hook(
+                "fixtures/bad-import.js",
+                "fixtures/nested/module.js",
+                ResolveHookStage::Fallback,
+                resolution,
+            );
+            assert!(ret.errors.is_empty());
+            assert!(ret.files.is_empty());
+        }
+
+        // Imports the resolver finds don't reach a fallback hook.
+        let ret = collect_with_hook(
+            "fixtures/nested/module.spec.js",
+            "fixtures/nested/module.js",
+            ResolveHookStage::Fallback,
+            HookResolution::Ignore,
+        );
+        assert_eq!(ret.files, vec!["fixtures/nested/module.spec.js"]);
+    }
+
+    #[test]
+    fn test_resolve_hook_before() {
+        let ret = collect_with_hook(
+            "fixtures/nested/module.spec.js",
+            "fixtures/nested/module.js",
+            ResolveHookStage::Before,
+            HookResolution::Ignore,
+        );
+        assert!(ret.errors.is_empty());
+        assert!(ret.files.is_empty());
+
+        let ret = collect_with_hook(
+            "fixtures/nested/module.spec.js",
+            "fixtures/nested/all.js",
+            ResolveHookStage::Before,
+            HookResolution::Path(PathBuf::from("fixtures/nested/all.js")),
+        );
+        assert_eq!(ret.files, vec!["fixtures/nested/module.spec.js"]);
+    }
+
+    #[test]
+    fn test_implicit_dependencies() {
+        let options = AffectedOptions {
+            implicit_dependencies: vec![
+                ImplicitDependency {
+                    dependents: "fixtures/implicit/*.spec.js".to_string(),
+                    dependencies: "{dir}/__fixtures__/**".to_string(),
+                },
+                ImplicitDependency {
+                    dependents: "**/*.spec.js".to_string(),
+                    dependencies: "{dir}/__snapshots__/{name}.snap".to_string(),
+                },
+                ImplicitDependency {
+                    dependents: "fixtures/implicit/schema.spec.js".to_string(),
+                    dependencies: "fixtures/implicit/migrations/*.sql".to_string(),
+                },
+            ],
+            ..AffectedOptions::default()
+        };
+        let test_files = vec![
+            "fixtures/implicit/parser.spec.js",
+            "fixtures/implicit/schema.spec.js",
+        ];
+        let affected = |change: &str| {
+            let resolver = Resolver::new(ResolveOptions::default());
+            collect_affected_ok(test_files.clone(), vec![change], resolver, &options).files
+        };
+        assert_eq!(
+            affected("fixtures/implicit/__fixtures__/select.sql"),
+            vec![
+                "fixtures/implicit/parser.spec.js",
+                "fixtures/implicit/schema.spec.js"
+            ]
+        );
+        assert_eq!(
+            affected("fixtures/implicit/__snapshots__/parser.spec.js.snap"),
+            vec!["fixtures/implicit/parser.spec.js"]
+        );
+        assert_eq!(
+            affected("fixtures/implicit/migrations/001_users.sql"),
+            vec!["fixtures/implicit/schema.spec.js"]
+        );
+        assert_eq!(
+            affected("deleted:fixtures/implicit/__fixtures__/update.sql"),
+            vec![
+                "fixtures/implicit/parser.spec.js",
+                "fixtures/implicit/schema.spec.js"
+            ]
+        );
+        assert_eq!(
+            affected("deleted:fixtures/implicit/__snapshots__/schema.spec.js.snap"),
+            vec!["fixtures/implicit/schema.spec.js"]
+        );
+        assert_eq!(
+            affected("added:fixtures/implicit/migrations/002_posts.sql"),
+            vec!["fixtures/implicit/schema.spec.js"]
+        );
+        assert_eq!(
+            affected(
+                "renamed:fixtures/implicit/notes.sql\tfixtures/implicit/__snapshots__/schema.spec.js.snap"
+            ),
+            vec!["fixtures/implicit/schema.spec.js"]
+        );
+    }
+
+    #[test]
+    fn test_asset_references() {
+        let test_files = vec!["fixtures/assets/app.spec.js"];
+        assert_affected(test_files.clone(), vec!["fixtures/assets/worker.js"]);
+        assert_affected(test_files.clone(), vec!["fixtures/assets/helper.wasm"]);
+        assert_affected(test_files, vec!["fixtures/assets/config.json"]);
+    }
+
+    #[test]
+    fn test_stylesheets() {
+        let test_files = vec![
+            "fixtures/styles/button.spec.js",
+            "fixtures/styles/card.spec.js",
+            "fixtures/styles/page.spec.js",
+        ];
+        let affected = |changes: Vec<&str>| {
+            let resolver = Resolver::new(ResolveOptions::default());
+            collect_affected_ok(
+                test_files.clone(),
+                changes,
+                resolver,
+                &AffectedOptions::default(),
+            )
+            .files
+        };
+        assert_eq!(
+            affected(vec!["fixtures/styles/shared/_spacing.scss"]),
+            vec!["fixtures/styles/button.spec.js"]
+        );
+        assert_eq!(
+            affected(vec!["fixtures/styles/components/button-bg.png"]),
+            vec!["fixtures/styles/button.spec.js"]
+        );
+        assert_eq!(
+            affected(vec!["fixtures/styles/theme.less"]),
+            vec!["fixtures/styles/card.spec.js"]
+        );
+        assert_eq!(
+            affected(vec!["fixtures/styles/base.css"]),
+            vec!["fixtures/styles/page.spec.js"]
+        );
+        // Missing assets aren't errors, but deleting one affects its users.
+        assert_eq!(
+            affected(vec!["deleted:fixtures/styles/fonts/brand.woff2"]),
+            vec!["fixtures/styles/page.spec.js"]
+        );
+    }
+
+    #[test]
+    fn test_single_file_components() {
+        let test_files = vec!["fixtures/sfc/button.spec.js", "fixtures/sfc/page.spec.js"];
+        let resolver = || {
+            Resolver::new(ResolveOptions {
+                extensions: vec![".ts".into(), ".js".into()],
+                ..ResolveOptions::default()
+            })
+        };
+        let affected = |changes: Vec<&str>| {
+            collect_affected_ok(
+                test_files.clone(),
+                changes,
+                resolver(),
+                &AffectedOptions::default(),
+            )
+            .files
+        };
+        assert_eq!(
+            affected(vec!["fixtures/sfc/cx.ts"]),
+            vec!["fixtures/sfc/button.spec.js"]
+        );
+        assert_eq!(
+            affected(vec!["fixtures/sfc/styles/_colors.scss"]),
+            vec!["fixtures/sfc/button.spec.js"]
+        );
+        assert_eq!(
+            affected(vec!["fixtures/sfc/counter.js"]),
+            vec!["fixtures/sfc/page.spec.js"]
+        );
+    }
+
+    #[test]
+    fn test_mdx() {
+        assert_affected(
+            vec!["fixtures/mdx/Button.mdx"],
+            vec!["fixtures/mdx/Button.js"],
+        );
+    }
+
+    #[test]
+    fn test_html_entry() {
+        let test_files = vec!["fixtures/html/index.html"];
+        assert_affected(test_files.clone(), vec!["fixtures/html/src/app.js"]);
+        assert_affected(test_files.clone(), vec!["fixtures/html/src/style.css"]);
+        // Assets served from elsewhere, like a public directory, aren't errors.
+        assert_affected(test_files, vec!["deleted:fixtures/html/favicon.ico"]);
+    }
+
+    #[test]
+    fn test_graphql_fragments() {
+        assert_collect_affected(
+            vec![
+                "fixtures/graphql/user.spec.js",
+                "fixtures/graphql/viewer.spec.js",
+            ],
+            vec!["fixtures/graphql/fragments/Avatar.graphql"],
+            vec!["fixtures/graphql/user.spec.js"],
+            Resolver::new(ResolveOptions::default()),
+        );
+    }
+
+    #[test]
+    fn test_triple_slash_reference() {
+        let resolver = || {
+            Resolver::new(ResolveOptions {
+                extensions: vec![".ts".into()],
+                ..ResolveOptions::default()
+            })
+        };
+        let test_files = vec!["fixtures/references/version.spec.ts"];
+        let changes = vec!["fixtures/references/globals.d.ts"];
+        assert_collect_affected_with(
+            test_files.clone(),
+            changes.clone(),
+            test_files.clone(),
+            resolver(),
+            false,
+        );
+        assert_collect_affected_with(test_files, changes, vec![], resolver(), true);
+    }
+
+    #[test]
+    fn test_triple_slash_types_references() {
+        let test_files = vec!["fixtures/references/types.spec.ts"];
+        let resolver = || {
+            Resolver::new(ResolveOptions {
+                extensions: vec![".ts".into()],
+                ..ResolveOptions::default()
+            })
+        };
+        for change in [
+            "fixtures/references/node_modules/@types/node/index.d.ts",
+            "fixtures/references/node_modules/typed-lib/lib/typed.d.ts",
+            "npm:@types/node",
+            "npm:typed-lib",
+        ] {
+            assert_collect_affected(
+                test_files.clone(),
+                vec![change],
+                test_files.clone(),
+                resolver(),
+            );
+        }
+        assert_collect_affected(
+            test_files.clone(),
+            vec!["fixtures/references/node_modules/typed-lib/lib/index.js"],
+            vec![],
+            resolver(),
+        );
+        assert_collect_affected_with(
+            test_files,
+            vec!["npm:@types/node"],
+            vec![],
+            resolver(),
+            true,
+        );
+    }
+
+    #[test]
+    fn test_jsdoc_import_type() {
+        let test_files = vec!["fixtures/jsdoc/app.spec.js"];
+        let changes = vec!["fixtures/jsdoc/config.js"];
+        let resolver = || Resolver::new(ResolveOptions::default());
+        assert_collect_affected_with(
+            test_files.clone(),
+            changes.clone(),
+            test_files.clone(),
+            resolver(),
+            false,
+        );
+        assert_collect_affected_with(test_files, changes, vec![], resolver(), true);
+    }
+
+    #[test]
+    fn test_ambient_declarations() {
+        let test_files = vec![
+            "fixtures/ambient/format.spec.ts",
+            "fixtures/ambient/plain.spec.js",
+            "fixtures/ambient/legacy.spec.js",
+        ];
+        let affected = |changes: Vec<&str>, ignore_type_imports: bool| {
+            let options = AffectedOptions {
+                ignore_type_imports,
+                ..AffectedOptions::default()
+            };
+            let ret =
+                collect_affected_ok(test_files.clone(), changes, ambient_resolver(), &options);
+            (ret.files, ret.type_files)
+        };
+        let type_level = vec![
+            "fixtures/ambient/format.spec.ts".to_string(),
+            "fixtures/ambient/plain.spec.js".to_string(),
+        ];
+        assert_eq!(
+            affected(vec!["fixtures/ambient/types/global.d.ts"], false),
+            (vec![], type_level.clone())
+        );
+        assert_eq!(
+            affected(vec!["fixtures/ambient/types/augment.d.ts"], false),
+            (vec![], type_level.clone())
+        );
+        assert_eq!(
+            affected(vec!["npm:@types/node"], false),
+            (vec![], type_level)
+        );
+        assert_eq!(
+            affected(vec!["fixtures/ambient/types/money.d.ts"], false),
+            (vec![], vec![])
+        );
+        assert_eq!(
+            affected(vec!["fixtures/ambient/types/global.d.ts"], true),
+            (vec![], vec![])
+        );
+        // Files affected through imports are only reported once.
+        assert_eq!(
+            affected(
+                vec![
+                    "fixtures/ambient/src/format.ts",
+                    "fixtures/ambient/types/global.d.ts"
+                ],
+                false
+            ),
+            (
+                vec![
+                    "fixtures/ambient/format.spec.ts".to_string(),
+                    "fixtures/ambient/plain.spec.js".to_string(),
+                ],
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn test_ambient_declarations_discovered_tsconfig() {
+        let test_files = vec!["fixtures/ambient-default/src/format.spec.ts"];
+        let affected = |changes: Vec<&str>| {
+            let resolver = Resolver::new(ResolveOptions {
+                extensions: vec![".ts".into()],
+                tsconfig: Some(TsconfigDiscovery::Auto),
+                ..ResolveOptions::default()
+            });
+            collect_affected_ok(
+                test_files.clone(),
+                changes,
+                resolver,
+                &AffectedOptions::default(),
+            )
+            .type_files
+        };
+        assert_eq!(affected(vec!["npm:@types/node"]), test_files);
+        assert_eq!(
+            affected(vec!["fixtures/ambient-default/types/global.d.ts"]),
+            test_files
+        );
+        assert!(affected(vec!["npm:lodash"]).is_empty());
+    }
+
     #[test]
     fn test_nested() {
         let test_files = [
@@ -483,8 +830,26 @@ mod tests {
         let ret = collect_affected(vec![file_name], vec![], Resolver::default(), false, vec![]);
         assert_eq!(
             ret.errors,
-            vec![format!("[{file_name}]\nCannot find module 'bad-import'")]
+            vec![AffectedError::Resolve {
+                file: file_name.to_string(),
+                specifier: "bad-import".to_string(),
+                message: "Cannot find module 'bad-import'".to_string(),
+            }]
+        );
+        assert_eq!(
+            ret.errors[0].to_string(),
+            format!("[{file_name}]\nCannot find module 'bad-import'")
+        );
+        // The search stops at changed files, so their errors aren't reported.
+        let ret = collect_affected(
+            vec![file_name],
+            vec![file_name],
+            Resolver::default(),
+            false,
+            vec![],
         );
+        assert_eq!(ret.files, vec![file_name]);
+        assert!(ret.errors.is_empty());
     }
 
     #[test]
@@ -609,6 +974,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_try_collect_affected_invalid_entry() {
+        let err = try_collect_affected(
+            vec!["fixtures/npm/uses-lodash.js"],
+            vec!["npm:"],
+            Resolver::new(ResolveOptions::default()),
+            false,
+            vec![],
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err,
+            InputError::InvalidChangedEntry {
+                entry: "npm:".to_string(),
+                reason: "missing package name after 'npm:'".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_try_collect_affected_invalid_require_alias() {
+        let err = try_collect_affected(
+            vec!["fixtures/npm/uses-lodash.js"],
+            vec![],
+            Resolver::new(ResolveOptions::default()),
+            false,
+            vec!["jest.requireActual.extra"],
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, InputError::InvalidRequireAlias { .. }));
+    }
+
+    #[test]
+    fn test_try_collect_affected_invalid_import_rule() {
+        let options = AffectedOptions {
+            import_rules: vec![ImportRule {
+                callee: "i18n.".to_string(),
+                ..ImportRule::default()
+            }],
+            ..AffectedOptions::default()
+        };
+        let err = try_collect_affected_with_options(
+            vec!["fixtures/npm/uses-lodash.js"],
+            vec![],
+            Resolver::new(ResolveOptions::default()),
+            &options,
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, InputError::InvalidImportRule { .. }));
+    }
+
+    #[test]
+    fn test_try_collect_affected_valid_input() {
+        let ret = try_collect_affected(
+            vec!["fixtures/npm/uses-lodash.js"],
+            vec!["npm:lodash"],
+            Resolver::new(ResolveOptions::default()),
+            false,
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(ret.files, vec!["fixtures/npm/uses-lodash.js"]);
+    }
+
     #[test]
     fn test_npm_subpath_entry_matches_subpath() {
         // npm:lodash/fp matches imports of lodash/fp.
@@ -675,7 +1107,7 @@ mod tests {
             vec![],
         );
         assert_eq!(ret.errors.len(), 1);
-        assert!(ret.errors[0].contains("not-installed-pkg"));
+        assert_eq!(ret.errors[0].specifier(), Some("not-installed-pkg"));
         assert!(ret.files.is_empty());
     }
 
@@ -748,6 +1180,358 @@ mod tests {
         assert!(ret.files.is_empty());
     }
 
+    #[test]
+    fn test_expand_dynamic_imports() {
+        let affected = |expand_dynamic_imports: bool, changes: Vec<&str>| {
+            let options = AffectedOptions {
+                expand_dynamic_imports,
+                ..AffectedOptions::default()
+            };
+            collect_affected_with_options(
+                vec!["fixtures/dynamic-import/i18n.spec.js"],
+                changes,
+                Resolver::new(ResolveOptions::default()),
+                &options,
+            )
+        };
+
+        let ret = affected(true, vec!["fixtures/dynamic-import/locales/en.json"]);
+        assert!(ret.errors.is_empty(), "unexpected errors: {:?}", ret.errors);
+        assert_eq!(ret.files, vec!["fixtures/dynamic-import/i18n.spec.js"]);
+        let ret = affected(true, vec!["fixtures/dynamic-import/pages/home.js"]);
+        assert_eq!(ret.files, vec!["fixtures/dynamic-import/i18n.spec.js"]);
+        let ret = affected(true, vec!["fixtures/dynamic-import/pages/admin/users.js"]);
+        assert!(ret.files.is_empty());
+
+        let ret = affected(false, vec!["fixtures/dynamic-import/locales/en.json"]);
+        assert!(ret.files.is_empty());
+        assert_eq!(ret.errors.len(), 2);
+    }
+
+    // ---- explain ----------------------------------------------------------
+
+    fn explain(test_files: Vec<&str>, changes: Vec<&str>) -> Vec<Explanation> {
+        let options = AffectedOptions {
+            explain: true,
+            ..AffectedOptions::default()
+        };
+        collect_affected_ok(
+            test_files,
+            changes,
+            Resolver::new(ResolveOptions::default()),
+            &options,
+        )
+        .explanations
+    }
+
+    #[test]
+    fn test_explain_disabled_by_default() {
+        let ret = collect_affected(
+            vec!["fixtures/nested/module.spec.js"],
+            vec!["fixtures/nested/module.js"],
+            Resolver::new(ResolveOptions::default()),
+            false,
+            vec![],
+        );
+        assert!(ret.explanations.is_empty());
+    }
+
+    #[test]
+    fn test_explain_file_change() {
+        assert_eq!(
+            explain(
+                vec!["fixtures/nested/module.spec.js"],
+                vec!["fixtures/nested/another-module.js"],
+            ),
+            vec![Explanation {
+                file: "fixtures/nested/module.spec.js".to_string(),
+                chain: vec![
+                    "fixtures/nested/module.spec.js".to_string(),
+                    "fixtures/nested/module.js".to_string(),
+                    "fixtures/nested/sub-module.js".to_string(),
+                    "fixtures/nested/another-module.js".to_string(),
+                ],
+                reason: AffectedReason::FileChanged,
+            }],
+        );
+    }
+
+    #[test]
+    fn test_explain_changed_test_file() {
+        assert_eq!(
+            explain(
+                vec!["fixtures/nested/module.spec.js"],
+                vec!["fixtures/nested/module.spec.js"],
+            ),
+            vec![Explanation {
+                file: "fixtures/nested/module.spec.js".to_string(),
+                chain: vec!["fixtures/nested/module.spec.js".to_string()],
+                reason: AffectedReason::FileChanged,
+            }],
+        );
+    }
+
+    #[test]
+    fn test_explain_node_modules_match() {
+        assert_eq!(
+            explain(vec!["fixtures/npm/consumer.spec.js"], vec!["npm:lodash"]),
+            vec![Explanation {
+                file: "fixtures/npm/consumer.spec.js".to_string(),
+                chain: vec![
+                    "fixtures/npm/consumer.spec.js".to_string(),
+                    "fixtures/npm/intermediate.js".to_string(),
+                    "fixtures/npm/node_modules/lodash/index.js".to_string(),
+                ],
+                reason: AffectedReason::NodeModulesMatched {
+                    package: "lodash".to_string(),
+                },
+            }],
+        );
+    }
+
+    #[test]
+    fn test_explain_npm_fallback() {
+        assert_eq!(
+            explain(
+                vec!["fixtures/unresolved-pkg/uses-not-installed-deep.js"],
+                vec!["npm:not-installed-pkg"],
+            ),
+            vec![Explanation {
+                file: "fixtures/unresolved-pkg/uses-not-installed-deep.js".to_string(),
+                chain: vec!["fixtures/unresolved-pkg/uses-not-installed-deep.js".to_string()],
+                reason: AffectedReason::NpmFallbackMatched {
+                    specifier: "not-installed-pkg/sub/path".to_string(),
+                    package: "not-installed-pkg".to_string(),
+                },
+            }],
+        );
+    }
+
+    #[test]
+    fn test_explain_ambient_declaration() {
+        let options = AffectedOptions {
+            explain: true,
+            ..AffectedOptions::default()
+        };
+        let ret = collect_affected_with_options(
+            vec!["fixtures/ambient/plain.spec.js"],
+            vec!["fixtures/ambient/types/global.d.ts"],
+            ambient_resolver(),
+            &options,
+        );
+        assert_eq!(
+            ret.explanations,
+            vec![Explanation {
+                file: "fixtures/ambient/plain.spec.js".to_string(),
+                chain: vec![
+                    "fixtures/ambient/plain.spec.js".to_string(),
+                    "fixtures/ambient/src/format.ts".to_string(),
+                ],
+                reason: AffectedReason::AmbientDeclarationChanged {
+                    path: "fixtures/ambient/types/global.d.ts".to_string(),
+                },
+            }],
+        );
+    }
+
+    #[test]
+    fn test_explain_unaffected_files_omitted() {
+        assert!(explain(
+            vec!["fixtures/nested/circular.spec.js"],
+            vec!["fixtures/nested/another-module.js"],
+        )
+        .is_empty());
+    }
+
+    // ---- deleted and renamed files ---------------------------------------
+
+    #[test]
+    fn test_deleted_file_affects_importers() {
+        assert_affected(
+            vec!["fixtures/removed/deleted.spec.js"],
+            vec!["deleted:fixtures/removed/deleted-helper.js"],
+        );
+    }
+
+    #[test]
+    fn test_renamed_file_affects_importers_of_old_path() {
+        assert_affected(
+            vec!["fixtures/removed/renamed.spec.js"],
+            vec!["renamed:fixtures/removed/old-name.js\tfixtures/removed/new-name.js"],
+        );
+    }
+
+    #[test]
+    fn test_import_of_missing_file_still_errors_without_deletion() {
+        let ret = collect_affected(
+            vec!["fixtures/removed/deleted.spec.js"],
+            vec!["deleted:fixtures/removed/other.js"],
+            Resolver::default(),
+            false,
+            vec![],
+        );
+        assert!(ret.files.is_empty());
+        assert_eq!(
+            ret.errors,
+            vec![AffectedError::Resolve {
+                file: "fixtures/removed/uses-deleted.js".to_string(),
+                specifier: "./deleted-helper".to_string(),
+                message: "Cannot find module './deleted-helper'".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_explain_removed_file_imported() {
+        assert_eq!(
+            explain(
+                vec!["fixtures/removed/deleted.spec.js"],
+                vec!["deleted:fixtures/removed/deleted-helper.js"],
+            ),
+            vec![Explanation {
+                file: "fixtures/removed/deleted.spec.js".to_string(),
+                chain: vec![
+                    "fixtures/removed/deleted.spec.js".to_string(),
+                    "fixtures/removed/uses-deleted.js".to_string(),
+                ],
+                reason: AffectedReason::RemovedFileImported {
+                    specifier: "./deleted-helper".to_string(),
+                    path: "fixtures/removed/deleted-helper.js".to_string(),
+                },
+            }],
+        );
+    }
+
+    #[test]
+    fn test_explain_implicit_dependency_changed() {
+        let options = AffectedOptions {
+            explain: true,
+            implicit_dependencies: vec![ImplicitDependency {
+                dependents: "**/*.spec.js".to_string(),
+                dependencies: "{dir}/__snapshots__/{name}.snap".to_string(),
+            }],
+            ..AffectedOptions::default()
+        };
+        let ret = collect_affected_ok(
+            vec!["fixtures/implicit/schema.spec.js"],
+            vec!["added:fixtures/implicit/__snapshots__/schema.spec.js.snap"],
+            Resolver::new(ResolveOptions::default()),
+            &options,
+        );
+        assert_eq!(
+            ret.explanations,
+            vec![Explanation {
+                file: "fixtures/implicit/schema.spec.js".to_string(),
+                chain: vec!["fixtures/implicit/schema.spec.js".to_string()],
+                reason: AffectedReason::ImplicitDependencyChanged {
+                    pattern: "fixtures/implicit/__snapshots__/schema.spec.js.snap".to_string(),
+                    path: "fixtures/implicit/__snapshots__/schema.spec.js.snap".to_string(),
+                },
+            }],
+        );
+    }
+
+    // ---- symbol tracking through barrel files ------------------------------
+
+    const BARREL_SPECS: [&str; 5] = [
+        "fixtures/barrel/all.spec.js",
+        "fixtures/barrel/button.spec.js",
+        "fixtures/barrel/icon.spec.js",
+        "fixtures/barrel/modal.spec.js",
+        "fixtures/barrel/ui-button.spec.js",
+    ];
+
+    fn barrel_affected(changes: Vec<&str>, track_symbols: bool) -> Vec<String> {
+        let options = AffectedOptions {
+            track_symbols,
+            ..AffectedOptions::default()
+        };
+        let mut files = collect_affected_ok(
+            BARREL_SPECS.to_vec(),
+            changes,
+            Resolver::new(ResolveOptions::default()),
+            &options,
+        )
+        .files;
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_barrel_without_symbol_tracking() {
+        assert_eq!(
+            barrel_affected(vec!["fixtures/barrel/components/Modal.js"], false),
+            BARREL_SPECS.to_vec(),
+        );
+    }
+
+    #[test]
+    fn test_barrel_named_reexport() {
+        assert_eq!(
+            barrel_affected(vec!["fixtures/barrel/components/Modal.js"], true),
+            vec![
+                "fixtures/barrel/all.spec.js",
+                "fixtures/barrel/modal.spec.js"
+            ],
+        );
+    }
+
+    #[test]
+    fn test_barrel_default_reexport() {
+        assert_eq!(
+            barrel_affected(vec!["fixtures/barrel/components/Icon.js"], true),
+            vec![
+                "fixtures/barrel/all.spec.js",
+                "fixtures/barrel/icon.spec.js"
+            ],
+        );
+    }
+
+    #[test]
+    fn test_barrel_star_reexport_through_dependency() {
+        assert_eq!(
+            barrel_affected(vec!["fixtures/barrel/utils/cx.js"], true),
+            vec![
+                "fixtures/barrel/all.spec.js",
+                "fixtures/barrel/button.spec.js",
+                "fixtures/barrel/ui-button.spec.js",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_barrel_itself_changed() {
+        assert_eq!(
+            barrel_affected(vec!["fixtures/barrel/components/index.js"], true),
+            BARREL_SPECS.to_vec(),
+        );
+    }
+
+    #[test]
+    fn test_barrel_explain_chain() {
+        let options = AffectedOptions {
+            track_symbols: true,
+            explain: true,
+            ..AffectedOptions::default()
+        };
+        let ret = collect_affected_with_options(
+            vec!["fixtures/barrel/ui-button.spec.js"],
+            vec!["fixtures/barrel/utils/cx.js"],
+            Resolver::new(ResolveOptions::default()),
+            &options,
+        );
+        assert_eq!(
+            ret.explanations[0].chain,
+            vec![
+                "fixtures/barrel/ui-button.spec.js",
+                "fixtures/barrel/ui.js",
+                "fixtures/barrel/components/index.js",
+                "fixtures/barrel/components/Button.js",
+                "fixtures/barrel/utils/cx.js",
+            ],
+        );
+    }
+
     #[test]
     fn test_mixed_changeset_file_and_npm() {
         assert_collect_affected(
@@ -0,0 +1,535 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{Statement, TSModuleDeclarationName};
+use oxc_parser::Parser;
+use oxc_resolver::{ResolveError, ResolveOptions, Resolver, TsConfig, TsconfigDiscovery};
+use oxc_span::SourceType;
+
+use crate::changeset::{
+    bare_specifier_segments, matching_changed_package, ChangeKind, ChangedEntry,
+};
+use crate::glob::{static_prefix, to_slash, walk_files};
+
+/// What the tsconfigs of the scanned files make visible to every file they
+/// cover without an import: ambient declaration files, and the packages in
+/// `compilerOptions.types`.
+pub struct AmbientTypes {
+    configs: Vec<AmbientConfig>,
+}
+
+struct AmbientConfig {
+    tsconfig: Arc<TsConfig>,
+    /// `None` when no `compilerOptions.types` is set, which loads every
+    /// `@types` package.
+    types: Option<Vec<String>>,
+    /// The covered declaration files that are modules rather than ambient,
+    /// found when loading. Other declaration files, like ones created or
+    /// deleted since, are assumed to be ambient.
+    modules: HashSet<PathBuf>,
+}
+
+/// A change to something every file covered by a tsconfig sees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmbientChange {
+    /// An ambient declaration file was changed, added or removed.
+    Declaration(PathBuf),
+    /// A package loaded through `compilerOptions.types` was changed.
+    TypesPackage(String),
+}
+
+impl AmbientTypes {
+    /// Loads the tsconfig given to `resolver` with [`TsconfigDiscovery::Manual`],
+    /// or with [`TsconfigDiscovery::Auto`] the ones found for `files`.
+    pub fn load<'a>(
+        resolver: &Resolver,
+        files: impl IntoIterator<Item = &'a PathBuf>,
+    ) -> Option<Self> {
+        let tsconfigs = match resolver.options().tsconfig.as_ref()? {
+            TsconfigDiscovery::Manual(options) => {
+                vec![resolver.resolve_tsconfig(&options.config_file).ok()?]
+            }
+            TsconfigDiscovery::Auto => {
+                let mut tsconfigs: Vec<Arc<TsConfig>> = Vec::new();
+                for tsconfig in files
+                    .into_iter()
+                    .filter_map(|file| resolver.find_tsconfig(file).ok().flatten())
+                {
+                    if !tsconfigs.iter().any(|t| t.path() == tsconfig.path()) {
+                        tsconfigs.push(tsconfig);
+                    }
+                }
+                tsconfigs
+            }
+        };
+        if tsconfigs.is_empty() {
+            return None;
+        }
+        let configs = tsconfigs
+            .into_iter()
+            .map(|tsconfig| {
+                let mut config = AmbientConfig {
+                    types: read_types(resolver, tsconfig.path(), 0),
+                    tsconfig,
+                    modules: HashSet::new(),
+                };
+                config.modules = config.find_modules();
+                config
+            })
+            .collect();
+        Some(AmbientTypes { configs })
+    }
+
+    /// The files among `files` covered by a tsconfig that `changes` affect
+    /// as a whole, with the change that affects them.
+    pub fn changed_files<'a>(
+        &self,
+        changes: &[ChangedEntry],
+        files: impl IntoIterator<Item = &'a PathBuf>,
+    ) -> HashMap<&'a PathBuf, AmbientChange> {
+        let changed: Vec<(&AmbientConfig, AmbientChange)> = self
+            .configs
+            .iter()
+            .filter_map(|config| Some((config, config.find_change(changes)?)))
+            .collect();
+        if changed.is_empty() {
+            return HashMap::new();
+        }
+        files
+            .into_iter()
+            .filter_map(|file| {
+                changed
+                    .iter()
+                    .find(|(config, _)| config.covers(file))
+                    .map(|(_, change)| (file, change.clone()))
+            })
+            .collect()
+    }
+}
+
+impl AmbientConfig {
+    /// True if the tsconfig's `files`, or `include` minus `exclude`, cover `path`.
+    fn covers(&self, path: &Path) -> bool {
+        let tsconfig = &self.tsconfig;
+        if tsconfig
+            .files
+            .as_ref()
+            .is_some_and(|files| files.iter().any(|file| file == path))
+        {
+            return true;
+        }
+        if !self.has_source_extension(path) {
+            return false;
+        }
+        let matches = |patterns: &[PathBuf]| patterns.iter().any(|p| matches_pattern(p, path));
+        let included = match &tsconfig.include {
+            Some(include) => matches(include),
+            None => tsconfig.files.is_none() && path.starts_with(tsconfig.directory()),
+        };
+        let excluded = match &tsconfig.exclude {
+            Some(exclude) => matches(exclude),
+            None => path
+                .components()
+                .any(|c| c == Component::Normal("node_modules".as_ref())),
+        };
+        included && !excluded
+    }
+
+    /// Finds the covered declaration files that aren't ambient.
+    fn find_modules(&self) -> HashSet<PathBuf> {
+        let tsconfig = &self.tsconfig;
+        let mut roots: Vec<PathBuf> = match &tsconfig.include {
+            Some(include) => include
+                .iter()
+                .map(|pattern| include_root(pattern))
+                .collect(),
+            None if tsconfig.files.is_none() => vec![tsconfig.directory().to_path_buf()],
+            None => vec![],
+        };
+        roots.sort();
+        roots.dedup_by(|root, parent| root.starts_with(parent));
+        let mut declarations: Vec<PathBuf> = tsconfig
+            .files
+            .iter()
+            .flatten()
+            .filter(|file| is_declaration_file(file))
+            .cloned()
+            .collect();
+        for root in roots {
+            declarations.extend(walk_files(&root, false, &mut |path| {
+                is_declaration_file(path) && self.covers(path)
+            }));
+        }
+        declarations
+            .into_iter()
+            .filter(|path| !is_ambient(path))
+            .collect()
+    }
+
+    fn has_source_extension(&self, path: &Path) -> bool {
+        let allow_js = self.tsconfig.compiler_options.allow_js == Some(true);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ts" | "tsx" | "mts" | "cts") => true,
+            Some("js" | "jsx" | "mjs" | "cjs") => allow_js,
+            _ => false,
+        }
+    }
+
+    /// The first of `changes` that affects every covered file, if any.
+    fn find_change(&self, changes: &[ChangedEntry]) -> Option<AmbientChange> {
+        let changed_packages: HashSet<String> = changes
+            .iter()
+            .filter_map(|entry| match entry {
+                ChangedEntry::Package(name) => Some(name.clone()),
+                ChangedEntry::File { .. } => None,
+            })
+            .collect();
+        for entry in changes {
+            let ChangedEntry::File { path, kind } = entry else {
+                continue;
+            };
+            let from = match kind {
+                ChangeKind::Renamed { from } => Some(from),
+                _ => None,
+            };
+            let declaration = [Some(path), from].into_iter().flatten().find(|path| {
+                is_declaration_file(path) && self.covers(path) && !self.modules.contains(*path)
+            });
+            if let Some(path) = declaration {
+                return Some(AmbientChange::Declaration(path.clone()));
+            }
+        }
+        match &self.types {
+            Some(types) => types.iter().find_map(|types| {
+                types_packages(types)
+                    .iter()
+                    .filter_map(|package| bare_specifier_segments(package))
+                    .find_map(|segments| matching_changed_package(&segments, &changed_packages))
+                    .map(|package| AmbientChange::TypesPackage(package.clone()))
+            }),
+            None => changes.iter().find_map(|entry| match entry {
+                ChangedEntry::Package(name) if name.split('/').next() == Some("@types") => {
+                    Some(AmbientChange::TypesPackage(name.clone()))
+                }
+                _ => None,
+            }),
+        }
+    }
+}
+
+/// Like TypeScript, patterns whose last segment has no extension or
+/// wildcard name a directory and match everything below it.
+fn matches_pattern(pattern: &Path, path: &Path) -> bool {
+    let pattern = to_slash(pattern);
+    let last = pattern.rsplit('/').next().unwrap_or(&pattern);
+    let pattern = if last.contains(['.', '*', '?']) {
+        pattern
+    } else {
+        format!("{}/**/*", pattern.trim_end_matches('/'))
+    };
+    fast_glob::glob_match(&pattern, to_slash(path))
+}
+
+/// The directory below which an `include` pattern matches, see
+/// [`matches_pattern`].
+fn include_root(pattern: &Path) -> PathBuf {
+    let prefix = static_prefix(pattern);
+    let names_file = pattern
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().contains('.'));
+    match prefix.parent() {
+        Some(parent) if prefix == pattern && names_file => parent.to_path_buf(),
+        _ => prefix,
+    }
+}
+
+/// Reads `compilerOptions.types` from the tsconfig at `path`, or else from
+/// the ones it extends.
+fn read_types(resolver: &Resolver, path: &Path, depth: usize) -> Option<Vec<String>> {
+    // Guards against `extends` cycles.
+    if depth > 16 {
+        return None;
+    }
+    let mut json = fs::read_to_string(path).ok()?;
+    json_strip_comments::strip(&mut json).ok()?;
+    let config: serde_json::Value = serde_json::from_str(&json).ok()?;
+    if let Some(types) = config
+        .get("compilerOptions")
+        .and_then(|options| options.get("types"))
+        .and_then(|types| types.as_array())
+    {
+        return Some(
+            types
+                .iter()
+                .filter_map(|t| t.as_str().map(str::to_string))
+                .collect(),
+        );
+    }
+    let extends: Vec<&str> = match config.get("extends") {
+        Some(serde_json::Value::String(extends)) => vec![extends],
+        Some(serde_json::Value::Array(extends)) => {
+            extends.iter().filter_map(|e| e.as_str()).collect()
+        }
+        _ => vec![],
+    };
+    let dir = path.parent()?;
+    // Later configs override earlier ones.
+    extends.iter().rev().find_map(|extends| {
+        let path = resolve_extends(resolver, dir, extends)?;
+        read_types(resolver, &path, depth + 1)
+    })
+}
+
+/// Finds the tsconfig an `extends` entry in `dir` names: a path, with or
+/// without `.json`, or a package's `tsconfig.json` or subpath.
+fn resolve_extends(resolver: &Resolver, dir: &Path, extends: &str) -> Option<PathBuf> {
+    if extends.starts_with('.') || Path::new(extends).is_absolute() {
+        let path = dir.join(extends);
+        return [
+            path.clone(),
+            PathBuf::from(format!("{}.json", path.display())),
+        ]
+        .into_iter()
+        .find(|path| path.is_file());
+    }
+    let candidates = if extends.ends_with(".json") {
+        vec![extends.to_string()]
+    } else {
+        vec![
+            format!("{extends}/tsconfig.json"),
+            format!("{extends}.json"),
+        ]
+    };
+    candidates.iter().find_map(|candidate| {
+        resolver
+            .resolve(dir, candidate)
+            .ok()
+            .map(|resolution| resolution.into_path_buf())
+    })
+}
+
+/// The packages a `types` entry can refer to: the package itself and its
+/// DefinitelyTyped counterpart, like `node` and `@types/node`.
+fn types_packages(types: &str) -> [String; 2] {
+    let definitely_typed = match types.strip_prefix('@') {
+        Some(scoped) => format!("@types/{}", scoped.replacen('/', "__", 1)),
+        None => format!("@types/{types}"),
+    };
+    [types.to_string(), definitely_typed]
+}
+
+/// Resolves a `/// <reference types="name" />` directive in `dir` like
+/// TypeScript: `@types/name` first, then the `name` package itself, through
+/// their `types` or `typings` fields or `index.d.ts`.
+pub fn resolve_types_reference(
+    resolver: &Resolver,
+    dir: &Path,
+    name: &str,
+) -> Result<PathBuf, ResolveError> {
+    let types_resolver = resolver.clone_with_options(ResolveOptions {
+        extensions: vec![".d.ts".into()],
+        main_fields: vec!["types".into(), "typings".into()],
+        main_files: vec!["index".into()],
+        condition_names: vec!["types".into(), "import".into(), "require".into()],
+        ..resolver.options().clone()
+    });
+    let [package, definitely_typed] = types_packages(name);
+    types_resolver
+        .resolve(dir, &definitely_typed)
+        .or_else(|_| types_resolver.resolve(dir, &package))
+        .map(|resolution| resolution.into_path_buf())
+}
+
+fn is_declaration_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| {
+            [".d.ts", ".d.mts", ".d.cts"]
+                .iter()
+                .any(|e| name.ends_with(e))
+        })
+}
+
+/// True if the declaration file at `path` declares globals or augments
+/// modules: it's a script rather than a module, or has a `declare global` or
+/// `declare module "name"` block. Files that can't be read, like deleted
+/// ones, are assumed to be ambient.
+fn is_ambient(path: &Path) -> bool {
+    let Ok(source_text) = fs::read_to_string(path) else {
+        return true;
+    };
+    let allocator = Allocator::default();
+    let source_type = SourceType::from_path(path).unwrap_or_default();
+    let program = Parser::new(&allocator, &source_text, source_type)
+        .parse()
+        .program;
+    let mut is_module = false;
+    for statement in program.body.iter() {
+        match statement {
+            Statement::TSGlobalDeclaration(_) => return true,
+            Statement::TSModuleDeclaration(declaration)
+                if matches!(declaration.id, TSModuleDeclarationName::StringLiteral(_)) =>
+            {
+                return true;
+            }
+            _ => is_module |= statement.is_module_declaration(),
+        }
+    }
+    !is_module
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use oxc_resolver::{TsconfigOptions, TsconfigReferences};
+
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        env::current_dir().unwrap().join("fixtures/ambient")
+    }
+
+    /// The tsconfig in `fixtures/<dir>`.
+    fn config(dir: &str) -> AmbientConfig {
+        let resolver = Resolver::new(ResolveOptions {
+            tsconfig: Some(TsconfigDiscovery::Manual(TsconfigOptions {
+                config_file: env::current_dir()
+                    .unwrap()
+                    .join("fixtures")
+                    .join(dir)
+                    .join("tsconfig.json"),
+                references: TsconfigReferences::Auto,
+            })),
+            ..ResolveOptions::default()
+        });
+        let mut ambient = AmbientTypes::load(&resolver, []).unwrap();
+        ambient.configs.remove(0)
+    }
+
+    fn package(name: &str) -> ChangedEntry {
+        ChangedEntry::Package(name.to_string())
+    }
+
+    #[test]
+    fn test_covers() {
+        let ambient = config("ambient");
+        assert!(ambient.covers(&fixtures().join("src/format.ts")));
+        assert!(ambient.covers(&fixtures().join("types/global.d.ts")));
+        assert!(ambient.covers(&fixtures().join("format.spec.ts")));
+        assert!(!ambient.covers(&fixtures().join("src/legacy/old.ts")));
+        assert!(!ambient.covers(&fixtures().join("src/format.js")));
+        assert!(!ambient.covers(&fixtures().join("other/format.ts")));
+    }
+
+    #[test]
+    fn test_is_ambient() {
+        assert!(is_ambient(&fixtures().join("types/global.d.ts")));
+        assert!(is_ambient(&fixtures().join("types/augment.d.ts")));
+        assert!(!is_ambient(&fixtures().join("types/money.d.ts")));
+        assert!(is_ambient(&fixtures().join("types/deleted.d.ts")));
+    }
+
+    #[test]
+    fn test_modules_found_when_loading() {
+        let ambient = config("ambient");
+        assert_eq!(
+            ambient.modules,
+            HashSet::from([fixtures().join("types/money.d.ts")])
+        );
+        // Deleted declaration files aren't known, and are assumed ambient.
+        let deleted = fixtures().join("types/deleted.d.ts");
+        assert_eq!(
+            ambient.find_change(&[ChangedEntry::File {
+                path: deleted.clone(),
+                kind: ChangeKind::Deleted,
+            }]),
+            Some(AmbientChange::Declaration(deleted))
+        );
+    }
+
+    #[test]
+    fn test_find_change() {
+        let ambient = config("ambient");
+        let modified = |path: &str| ChangedEntry::modified(fixtures().join(path));
+        assert_eq!(
+            ambient.find_change(&[modified("src/format.ts"), modified("types/global.d.ts")]),
+            Some(AmbientChange::Declaration(
+                fixtures().join("types/global.d.ts")
+            ))
+        );
+        assert_eq!(ambient.find_change(&[modified("types/money.d.ts")]), None);
+        assert_eq!(
+            ambient.find_change(&[ChangedEntry::Package("@types/node".to_string())]),
+            Some(AmbientChange::TypesPackage("@types/node".to_string()))
+        );
+        assert_eq!(
+            ambient.find_change(&[ChangedEntry::Package("vitest".to_string())]),
+            Some(AmbientChange::TypesPackage("vitest".to_string()))
+        );
+        assert_eq!(
+            ambient.find_change(&[ChangedEntry::Package("lodash".to_string())]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_types_default_to_every_types_package() {
+        let ambient = config("ambient-default");
+        assert_eq!(ambient.types, None);
+        assert_eq!(
+            ambient.find_change(&[package("@types/node")]),
+            Some(AmbientChange::TypesPackage("@types/node".to_string()))
+        );
+        assert_eq!(ambient.find_change(&[package("node")]), None);
+        assert_eq!(ambient.find_change(&[package("lodash")]), None);
+    }
+
+    #[test]
+    fn test_types_from_extended_config() {
+        let ambient = config("ambient-extends");
+        assert_eq!(ambient.types, Some(vec!["jest".to_string()]));
+        assert_eq!(
+            ambient.find_change(&[package("@types/jest")]),
+            Some(AmbientChange::TypesPackage("@types/jest".to_string()))
+        );
+        assert_eq!(ambient.find_change(&[package("@types/node")]), None);
+    }
+
+    #[test]
+    fn test_discovered_tsconfigs() {
+        let resolver = Resolver::new(ResolveOptions {
+            tsconfig: Some(TsconfigDiscovery::Auto),
+            ..ResolveOptions::default()
+        });
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let default_spec = fixtures.join("ambient-default/src/format.spec.ts");
+        let extends_file = fixtures.join("ambient-extends/src/a.ts");
+        let files = [default_spec.clone(), extends_file.clone()];
+        let ambient = AmbientTypes::load(&resolver, &files).unwrap();
+        assert_eq!(ambient.configs.len(), 2);
+
+        let changed = ambient.changed_files(&[package("jest")], &files);
+        assert_eq!(
+            changed,
+            HashMap::from([(
+                &extends_file,
+                AmbientChange::TypesPackage("jest".to_string())
+            )])
+        );
+        // Without `types`, every `@types` package is loaded.
+        let changed = ambient.changed_files(&[package("@types/jest")], &files);
+        assert_eq!(changed.len(), 2);
+        let declaration = fixtures.join("ambient-default/types/global.d.ts");
+        let changed = ambient.changed_files(&[ChangedEntry::modified(declaration.clone())], &files);
+        assert_eq!(
+            changed,
+            HashMap::from([(&default_spec, AmbientChange::Declaration(declaration))])
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use xxhash_rust::xxh3::xxh3_128;
+
+use crate::affected::AffectedOptions;
+use crate::context::RequireContext;
+use crate::error::AffectedError;
+use crate::glob::ImportGlob;
+use crate::imports::ImportUsage;
+use crate::styles::StyleSyntax;
+
+/// Entries that haven't been used for this long are removed by
+/// [`ScanCache::prune`].
+const MAX_ENTRY_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedImport {
+    pub specifier: String,
+    pub usage: ImportUsage,
+    /// Set for imports from stylesheets, which resolve differently.
+    pub style: Option<StyleSyntax>,
+    /// Set for asset references, which aren't errors when they don't resolve.
+    #[serde(default)]
+    pub asset: bool,
+    /// Set for `/// <reference types="..." />` directives, which name a
+    /// types package rather than a module.
+    #[serde(default)]
+    pub types: bool,
+}
+
+/// The parse result of a single file, as stored on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedScan {
+    pub parser_errors: Vec<AffectedError>,
+    pub imports: Vec<CachedImport>,
+    pub contexts: Vec<RequireContext>,
+    pub globs: Vec<ImportGlob>,
+    pub exports: BTreeSet<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    content_hash: String,
+    scan: CachedScan,
+}
+
+/// Persistent per-file parse results. Entries are stored one file per
+/// scanned path and are only used while the file content hash still matches.
+/// Only parsing is cached: imports are resolved again on every run, since
+/// resolutions depend on files like `package.json` and tsconfigs anywhere on
+/// disk. Everything else that influences parsing (scan options, sovra
+/// version) goes into a fingerprint that is part of the entry name, so
+/// changing any of it starts from a cold cache.
+pub struct ScanCache {
+    dir: PathBuf,
+    fingerprint: String,
+}
+
+impl ScanCache {
+    pub fn new(dir: &Path, scan_options: &AffectedOptions) -> Self {
+        let fingerprint = format!(
+            "{}\0{}\0{:?}\0{:?}\0{}\0{}",
+            env!("CARGO_PKG_VERSION"),
+            scan_options.ignore_type_imports,
+            scan_options.require_aliases,
+            scan_options.import_rules,
+            scan_options.track_symbols,
+            scan_options.expand_dynamic_imports,
+        );
+        ScanCache {
+            dir: dir.to_path_buf(),
+            fingerprint: format!("{:032x}", xxh3_128(fingerprint.as_bytes())),
+        }
+    }
+
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        let key = format!("{}\0{}", self.fingerprint, path.to_string_lossy());
+        self.dir
+            .join(format!("{:032x}.json", xxh3_128(key.as_bytes())))
+    }
+
+    /// Returns the cached scan of `path` if it was stored for `source_text`.
+    pub fn get(&self, path: &Path, source_text: &str) -> Option<CachedScan> {
+        let data = fs::read(self.entry_path(path)).ok()?;
+        let entry: CacheEntry = serde_json::from_slice(&data).ok()?;
+        if entry.content_hash != content_hash(source_text) {
+            return None;
+        }
+        // Marks the entry as used, see `prune`.
+        let _ = fs::File::options()
+            .write(true)
+            .open(self.entry_path(path))
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        Some(entry.scan)
+    }
+
+    /// Removes entries, of any fingerprint, that haven't been used for
+    /// [`MAX_ENTRY_AGE`], along with temporary files left by interrupted
+    /// writes.
+    pub fn prune(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let is_stale = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > MAX_ENTRY_AGE);
+            if is_stale {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    /// Stores `scan` for `path`. Failures are ignored; the cache is only an
+    /// optimization.
+    pub fn put(&self, path: &Path, source_text: &str, scan: CachedScan) {
+        let entry = CacheEntry {
+            content_hash: content_hash(source_text),
+            scan,
+        };
+        let Ok(data) = serde_json::to_vec(&entry) else {
+            return;
+        };
+        if fs::create_dir_all(&self.dir).is_err() {
+            return;
+        }
+        // Write then rename, so concurrent runs never read half an entry.
+        let entry_path = self.entry_path(path);
+        let tmp_path = entry_path.with_extension(format!("{}.tmp", process::id()));
+        if fs::write(&tmp_path, data).is_ok() && fs::rename(&tmp_path, &entry_path).is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+    }
+}
+
+fn content_hash(source_text: &str) -> String {
+    format!("{:032x}", xxh3_128(source_text.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("sovra-cache-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn scan() -> CachedScan {
+        CachedScan {
+            parser_errors: vec![],
+            imports: vec![CachedImport {
+                specifier: "./hest".to_string(),
+                usage: ImportUsage::all(),
+                style: None,
+                asset: false,
+                types: false,
+            }],
+            contexts: vec![],
+            globs: vec![],
+            exports: BTreeSet::new(),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = cache_dir("roundtrip");
+        let cache = ScanCache::new(&dir, &AffectedOptions::default());
+        let path = Path::new("/proj/snel.js");
+        assert_eq!(cache.get(path, "import './hest';"), None);
+        cache.put(path, "import './hest';", scan());
+        assert_eq!(cache.get(path, "import './hest';"), Some(scan()));
+        assert_eq!(
+            cache.get(Path::new("/proj/other.js"), "import './hest';"),
+            None
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_content_change_misses() {
+        let dir = cache_dir("content");
+        let cache = ScanCache::new(&dir, &AffectedOptions::default());
+        let path = Path::new("/proj/snel.js");
+        cache.put(path, "import './hest';", scan());
+        assert_eq!(cache.get(path, "import './rein';"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_options_change_misses() {
+        let dir = cache_dir("options");
+        let path = Path::new("/proj/snel.js");
+        ScanCache::new(&dir, &AffectedOptions::default()).put(path, "import './hest';", scan());
+
+        let ignoring_types = ScanCache::new(
+            &dir,
+            &AffectedOptions {
+                ignore_type_imports: true,
+                ..AffectedOptions::default()
+            },
+        );
+        assert_eq!(ignoring_types.get(path, "import './hest';"), None);
+
+        let tracking_symbols = ScanCache::new(
+            &dir,
+            &AffectedOptions {
+                track_symbols: true,
+                ..AffectedOptions::default()
+            },
+        );
+        assert_eq!(tracking_symbols.get(path, "import './hest';"), None);
+
+        let expanding = ScanCache::new(
+            &dir,
+            &AffectedOptions {
+                expand_dynamic_imports: true,
+                ..AffectedOptions::default()
+            },
+        );
+        assert_eq!(expanding.get(path, "import './hest';"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = cache_dir("prune");
+        let cache = ScanCache::new(&dir, &AffectedOptions::default());
+        let used = Path::new("/proj/snel.js");
+        let unused = Path::new("/proj/hest.js");
+        cache.put(used, "import './hest';", scan());
+        cache.put(unused, "", scan());
+        let long_ago = SystemTime::now() - MAX_ENTRY_AGE - Duration::from_secs(60);
+        for path in [used, unused] {
+            fs::File::options()
+                .write(true)
+                .open(cache.entry_path(path))
+                .unwrap()
+                .set_modified(long_ago)
+                .unwrap();
+        }
+        assert_eq!(cache.get(used, "import './hest';"), Some(scan()));
+        cache.prune();
+        assert_eq!(cache.get(used, "import './hest';"), Some(scan()));
+        assert_eq!(cache.get(unused, ""), None);
+        assert!(!cache.entry_path(unused).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
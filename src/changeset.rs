@@ -1,28 +1,153 @@
 use std::{
     collections::HashSet,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
+use crate::error::InputError;
+
+/// How a [`ChangedEntry::File`] changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    /// The file was moved here from `from`, an absolute path.
+    Renamed {
+        from: PathBuf,
+    },
+}
+
+/// A parsed changeset entry. See [`crate::collect_affected`] for the string
+/// formats.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ChangedEntry {
-    File(PathBuf),
+    /// An absolute file path.
+    File { path: PathBuf, kind: ChangeKind },
+    /// An npm package name, optionally with a subpath.
     Package(String),
 }
 
-pub fn parse_changed_entry(entry: &str, current_dir: &Path) -> ChangedEntry {
+impl ChangedEntry {
+    pub fn modified(path: PathBuf) -> Self {
+        ChangedEntry::File {
+            path,
+            kind: ChangeKind::Modified,
+        }
+    }
+
+    /// Formats the entry as a string accepted by [`parse_changed_entry`],
+    /// with paths relative to `current_dir` when they are inside it.
+    pub fn format(&self, current_dir: &Path) -> String {
+        let relative = |path: &Path| {
+            path.strip_prefix(current_dir)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .into_owned()
+        };
+        match self {
+            ChangedEntry::Package(name) => format!("npm:{name}"),
+            ChangedEntry::File { path, kind } => match kind {
+                ChangeKind::Added => format!("added:{}", relative(path)),
+                ChangeKind::Modified => relative(path),
+                ChangeKind::Deleted => format!("deleted:{}", relative(path)),
+                ChangeKind::Renamed { from } => {
+                    format!("renamed:{}\t{}", relative(from), relative(path))
+                }
+            },
+        }
+    }
+}
+
+pub fn parse_changed_entry(entry: &str, current_dir: &Path) -> Result<ChangedEntry, InputError> {
+    let invalid = |reason: &str| InputError::InvalidChangedEntry {
+        entry: entry.to_string(),
+        reason: reason.to_string(),
+    };
     if let Some(rest) = entry.strip_prefix("npm:") {
-        assert!(
-            !rest.is_empty(),
-            "Invalid changeset entry '{entry}': missing package name after 'npm:'",
-        );
-        return ChangedEntry::Package(rest.to_string());
-    }
-    let path_part = entry.strip_prefix("file:").unwrap_or(entry);
-    assert!(
-        !path_part.is_empty(),
-        "Invalid changeset entry '{entry}': empty path",
-    );
-    ChangedEntry::File(current_dir.join(path_part))
+        if rest.is_empty() {
+            return Err(invalid("missing package name after 'npm:'"));
+        }
+        return Ok(ChangedEntry::Package(rest.to_string()));
+    }
+    if let Some(rest) = entry.strip_prefix("renamed:") {
+        let (from, to) = rest.split_once('\t').unwrap_or_default();
+        if from.is_empty() || to.is_empty() {
+            return Err(invalid("expected 'renamed:<from>\\t<to>'"));
+        }
+        return Ok(ChangedEntry::File {
+            path: current_dir.join(to),
+            kind: ChangeKind::Renamed {
+                from: current_dir.join(from),
+            },
+        });
+    }
+    let (kind, path_part) = if let Some(rest) = entry.strip_prefix("added:") {
+        (ChangeKind::Added, rest)
+    } else if let Some(rest) = entry.strip_prefix("deleted:") {
+        (ChangeKind::Deleted, rest)
+    } else {
+        (
+            ChangeKind::Modified,
+            entry.strip_prefix("file:").unwrap_or(entry),
+        )
+    };
+    if path_part.is_empty() {
+        return Err(invalid("empty path"));
+    }
+    Ok(ChangedEntry::File {
+        path: current_dir.join(path_part),
+        kind,
+    })
+}
+
+/// Resolves `.` and `..` components without touching the file system.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// True if `specifier`, imported from `importer`, plausibly resolved to
+/// `removed` before it was deleted or renamed. The resolver can't tell which
+/// file it would have picked anymore, so only relative and absolute
+/// specifiers are considered, and extensions and directory `main_files` are
+/// matched loosely.
+pub fn specifier_targets_removed(
+    importer: &Path,
+    specifier: &str,
+    removed: &Path,
+    main_files: &[String],
+) -> bool {
+    let is_relative = specifier == "."
+        || specifier == ".."
+        || specifier.starts_with("./")
+        || specifier.starts_with("../");
+    if !is_relative && !specifier.starts_with('/') {
+        return false;
+    }
+    let Some(importer_dir) = importer.parent() else {
+        return false;
+    };
+    let target = normalize(&importer_dir.join(specifier));
+    let removed = normalize(removed);
+    let removed_stem = removed.with_extension("");
+    if target == removed || target == removed_stem || target.with_extension("") == removed_stem {
+        return true;
+    }
+    // Directory imports resolve to one of the main files.
+    removed.parent() == Some(target.as_path())
+        && removed_stem
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| main_files.iter().any(|m| m == n))
 }
 
 /// Returns the path segments after the last `node_modules` (or other module
@@ -44,16 +169,14 @@ pub fn node_modules_segments(path: &Path, module_paths: &HashSet<&str>) -> Optio
     }
 }
 
-/// True if any prefix of `segments` (joined with `/`) is in `changed_packages`.
-/// Matching is segment-aware: `"lodash"` matches `["lodash", ...]` but not
-/// `["lodash-utils", ...]`.
-pub fn matches_changed_package(segments: &[String], changed_packages: &HashSet<String>) -> bool {
-    for end in 1..=segments.len() {
-        if changed_packages.contains(&segments[..end].join("/")) {
-            return true;
-        }
-    }
-    false
+/// Returns the shortest prefix of `segments` (joined with `/`) that is in
+/// `changed_packages`. Matching is segment-aware: `"lodash"` matches
+/// `["lodash", ...]` but not `["lodash-utils", ...]`.
+pub fn matching_changed_package<'a>(
+    segments: &[String],
+    changed_packages: &'a HashSet<String>,
+) -> Option<&'a String> {
+    (1..=segments.len()).find_map(|end| changed_packages.get(&segments[..end].join("/")))
 }
 
 /// If `specifier` is a bare module specifier (Node ESM sense — no relative
@@ -87,6 +210,12 @@ mod tests {
         PathBuf::from("/proj")
     }
 
+    fn assert_invalid(entry: &str) {
+        let err = parse_changed_entry(entry, &cwd()).unwrap_err();
+        assert!(matches!(err, InputError::InvalidChangedEntry { .. }));
+        assert!(err.to_string().starts_with("Invalid changeset entry"));
+    }
+
     fn mods() -> HashSet<&'static str> {
         HashSet::from(["node_modules"])
     }
@@ -104,37 +233,139 @@ mod tests {
     #[test]
     fn parse_plain_file_path() {
         assert_eq!(
-            parse_changed_entry("src/foo.ts", &cwd()),
-            ChangedEntry::File(PathBuf::from("/proj/src/foo.ts")),
+            parse_changed_entry("src/foo.ts", &cwd()).unwrap(),
+            ChangedEntry::modified(PathBuf::from("/proj/src/foo.ts")),
         );
     }
 
     #[test]
     fn parse_file_prefix() {
         assert_eq!(
-            parse_changed_entry("file:src/foo.ts", &cwd()),
-            ChangedEntry::File(PathBuf::from("/proj/src/foo.ts")),
+            parse_changed_entry("file:src/foo.ts", &cwd()).unwrap(),
+            ChangedEntry::modified(PathBuf::from("/proj/src/foo.ts")),
         );
     }
 
     #[test]
     fn parse_relative_file() {
         assert_eq!(
-            parse_changed_entry("./src/foo.ts", &cwd()),
-            ChangedEntry::File(PathBuf::from("/proj/./src/foo.ts")),
+            parse_changed_entry("./src/foo.ts", &cwd()).unwrap(),
+            ChangedEntry::modified(PathBuf::from("/proj/./src/foo.ts")),
+        );
+    }
+
+    #[test]
+    fn parse_added_and_deleted() {
+        assert_eq!(
+            parse_changed_entry("added:src/foo.ts", &cwd()).unwrap(),
+            ChangedEntry::File {
+                path: PathBuf::from("/proj/src/foo.ts"),
+                kind: ChangeKind::Added,
+            },
+        );
+        assert_eq!(
+            parse_changed_entry("deleted:src/foo.ts", &cwd()).unwrap(),
+            ChangedEntry::File {
+                path: PathBuf::from("/proj/src/foo.ts"),
+                kind: ChangeKind::Deleted,
+            },
+        );
+    }
+
+    #[test]
+    fn parse_renamed() {
+        assert_eq!(
+            parse_changed_entry("renamed:src/old.ts\tsrc/new.ts", &cwd()).unwrap(),
+            ChangedEntry::File {
+                path: PathBuf::from("/proj/src/new.ts"),
+                kind: ChangeKind::Renamed {
+                    from: PathBuf::from("/proj/src/old.ts"),
+                },
+            },
+        );
+    }
+
+    #[test]
+    fn parse_renamed_without_target_errors() {
+        assert_invalid("renamed:src/old.ts");
+    }
+
+    #[test]
+    fn parse_empty_deleted_errors() {
+        assert_invalid("deleted:");
+    }
+
+    #[test]
+    fn format_roundtrips() {
+        for entry in [
+            "src/foo.ts",
+            "added:src/foo.ts",
+            "deleted:src/foo.ts",
+            "renamed:src/old.ts\tsrc/new.ts",
+            "npm:@scope/foo",
+        ] {
+            assert_eq!(
+                parse_changed_entry(entry, &cwd()).unwrap().format(&cwd()),
+                entry
+            );
+        }
+        assert_eq!(
+            ChangedEntry::modified(PathBuf::from("/elsewhere/foo.ts")).format(&cwd()),
+            "/elsewhere/foo.ts",
         );
     }
 
+    // ---- specifier_targets_removed ----------------------------------------
+
+    fn targets(specifier: &str, removed: &str) -> bool {
+        specifier_targets_removed(
+            Path::new("/proj/src/app.ts"),
+            specifier,
+            Path::new(removed),
+            &["index".to_string()],
+        )
+    }
+
     #[test]
-    #[should_panic(expected = "Invalid changeset entry")]
-    fn parse_empty_entry_panics() {
-        parse_changed_entry("", &cwd());
+    fn targets_exact_path() {
+        assert!(targets("./foo.ts", "/proj/src/foo.ts"));
+        assert!(targets("../lib/foo.ts", "/proj/lib/foo.ts"));
+        assert!(targets("/proj/lib/foo.ts", "/proj/lib/foo.ts"));
     }
 
     #[test]
-    #[should_panic(expected = "Invalid changeset entry")]
-    fn parse_empty_file_prefix_panics() {
-        parse_changed_entry("file:", &cwd());
+    fn targets_without_extension() {
+        assert!(targets("./foo", "/proj/src/foo.ts"));
+        assert!(targets("./nested/../foo", "/proj/src/foo.ts"));
+    }
+
+    #[test]
+    fn targets_aliased_extension() {
+        // TypeScript ESM imports `./foo.js` for `foo.ts`.
+        assert!(targets("./foo.js", "/proj/src/foo.ts"));
+    }
+
+    #[test]
+    fn targets_directory_main_file() {
+        assert!(targets("./foo", "/proj/src/foo/index.ts"));
+        assert!(!targets("./foo", "/proj/src/foo/other.ts"));
+    }
+
+    #[test]
+    fn targets_ignores_bare_and_unrelated() {
+        assert!(!targets("foo", "/proj/src/foo.ts"));
+        assert!(!targets("./bar", "/proj/src/foo.ts"));
+        assert!(!targets("./foo", "/proj/lib/foo.ts"));
+    }
+
+    #[test]
+    fn parse_empty_entry_errors() {
+        assert_invalid("");
+    }
+
+    #[test]
+    fn parse_empty_file_prefix_errors() {
+        assert_invalid("file:");
     }
 
     // ---- parse_changed_entry: npm packages --------------------------------
@@ -142,7 +373,7 @@ mod tests {
     #[test]
     fn parse_npm_plain_package() {
         assert_eq!(
-            parse_changed_entry("npm:lodash", &cwd()),
+            parse_changed_entry("npm:lodash", &cwd()).unwrap(),
             ChangedEntry::Package("lodash".to_string()),
         );
     }
@@ -150,7 +381,7 @@ mod tests {
     #[test]
     fn parse_npm_scoped_package() {
         assert_eq!(
-            parse_changed_entry("npm:@scope/foo", &cwd()),
+            parse_changed_entry("npm:@scope/foo", &cwd()).unwrap(),
             ChangedEntry::Package("@scope/foo".to_string()),
         );
     }
@@ -160,7 +391,7 @@ mod tests {
         // `@scope` is just a package name from sovra's perspective — match by
         // segment prefix means it'll catch every `@scope/...` import.
         assert_eq!(
-            parse_changed_entry("npm:@scope", &cwd()),
+            parse_changed_entry("npm:@scope", &cwd()).unwrap(),
             ChangedEntry::Package("@scope".to_string()),
         );
     }
@@ -171,7 +402,7 @@ mod tests {
         // segment-prefix based, so this will match imports of `lodash/fp` and
         // anything under it, but not `lodash` alone.
         assert_eq!(
-            parse_changed_entry("npm:lodash/fp", &cwd()),
+            parse_changed_entry("npm:lodash/fp", &cwd()).unwrap(),
             ChangedEntry::Package("lodash/fp".to_string()),
         );
     }
@@ -179,15 +410,14 @@ mod tests {
     #[test]
     fn parse_npm_scoped_subpath_accepted() {
         assert_eq!(
-            parse_changed_entry("npm:@scope/foo/sub", &cwd()),
+            parse_changed_entry("npm:@scope/foo/sub", &cwd()).unwrap(),
             ChangedEntry::Package("@scope/foo/sub".to_string()),
         );
     }
 
     #[test]
-    #[should_panic(expected = "Invalid changeset entry")]
-    fn parse_npm_empty_panics() {
-        parse_changed_entry("npm:", &cwd());
+    fn parse_npm_empty_errors() {
+        assert_invalid("npm:");
     }
 
     // ---- node_modules_segments --------------------------------------------
@@ -244,73 +474,92 @@ mod tests {
         );
     }
 
-    // ---- matches_changed_package ------------------------------------------
+    // ---- matching_changed_package -----------------------------------------
 
     #[test]
     fn match_unscoped_package() {
-        assert!(matches_changed_package(
-            &segs(&["lodash", "index.js"]),
-            &pkgs(&["lodash"])
-        ));
+        assert!(
+            matching_changed_package(&segs(&["lodash", "index.js"]), &pkgs(&["lodash"])).is_some()
+        );
     }
 
     #[test]
     fn match_unscoped_via_deep_import() {
-        assert!(matches_changed_package(
-            &segs(&["lodash", "fp", "index.js"]),
-            &pkgs(&["lodash"])
-        ));
+        assert!(
+            matching_changed_package(&segs(&["lodash", "fp", "index.js"]), &pkgs(&["lodash"]))
+                .is_some()
+        );
     }
 
     #[test]
     fn match_scoped_package() {
-        assert!(matches_changed_package(
+        assert!(matching_changed_package(
             &segs(&["@scope", "foo", "index.js"]),
             &pkgs(&["@scope/foo"])
-        ));
+        )
+        .is_some());
     }
 
     #[test]
     fn match_scope_alone_treated_as_prefix() {
         // `@scope` as an entry catches every `@scope/...` package.
-        assert!(matches_changed_package(
+        assert!(matching_changed_package(
             &segs(&["@scope", "foo", "index.js"]),
             &pkgs(&["@scope"])
-        ));
-        assert!(matches_changed_package(
+        )
+        .is_some());
+        assert!(matching_changed_package(
             &segs(&["@scope", "bar", "dist", "x.js"]),
             &pkgs(&["@scope"])
-        ));
+        )
+        .is_some());
     }
 
     #[test]
     fn no_match_other_scope() {
-        assert!(!matches_changed_package(
+        assert!(matching_changed_package(
             &segs(&["@other", "foo", "index.js"]),
             &pkgs(&["@scope"])
-        ));
+        )
+        .is_none());
     }
 
     #[test]
     fn no_match_substring_only() {
         // `lodash` must not match `lodash-utils` — segment boundaries matter.
-        assert!(!matches_changed_package(
-            &segs(&["lodash-utils", "index.js"]),
-            &pkgs(&["lodash"])
-        ));
+        assert!(
+            matching_changed_package(&segs(&["lodash-utils", "index.js"]), &pkgs(&["lodash"]))
+                .is_none()
+        );
     }
 
     #[test]
     fn match_subpath_entry() {
         // npm:lodash/fp matches `lodash/fp/...` but not `lodash` alone.
-        assert!(matches_changed_package(
+        assert!(matching_changed_package(
             &segs(&["lodash", "fp", "index.js"]),
             &pkgs(&["lodash/fp"])
-        ));
-        assert!(!matches_changed_package(
-            &segs(&["lodash", "index.js"]),
-            &pkgs(&["lodash/fp"])
-        ));
+        )
+        .is_some());
+        assert!(
+            matching_changed_package(&segs(&["lodash", "index.js"]), &pkgs(&["lodash/fp"]))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn matching_returns_shortest_entry() {
+        assert_eq!(
+            matching_changed_package(
+                &segs(&["@scope", "foo", "index.js"]),
+                &pkgs(&["@scope/foo", "@scope"])
+            ),
+            Some(&"@scope".to_string()),
+        );
+        assert_eq!(
+            matching_changed_package(&segs(&["lodash", "index.js"]), &pkgs(&["lodash/fp"])),
+            None,
+        );
     }
 
     // ---- bare_specifier_segments ------------------------------------------
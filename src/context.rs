@@ -0,0 +1,123 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::changeset::normalize;
+use crate::regexp::regexp_matches;
+
+/// A webpack `require.context(directory, recursive, regExp)` call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequireContext {
+    pub directory: String,
+    pub recursive: bool,
+    /// Pattern and flags of the `regExp` argument. It's tested against the
+    /// path of every file relative to `directory`, prefixed with `./`.
+    pub filter: Option<(String, String)>,
+}
+
+impl RequireContext {
+    /// Lists the files the context matches, relative to `base`, the
+    /// directory of the file that calls `require.context`. Returns `None` if
+    /// the directory doesn't exist. Like the resolver, `node_modules` folders
+    /// are skipped.
+    pub fn files(&self, base: &Path) -> Option<Vec<PathBuf>> {
+        let directory = normalize(&base.join(&self.directory));
+        if !directory.is_dir() {
+            return None;
+        }
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut stack: Vec<PathBuf> = vec![directory.clone()];
+        while let Some(dir) = stack.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                if file_type.is_dir() {
+                    if self.recursive && entry.file_name() != "node_modules" {
+                        stack.push(path);
+                    }
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+
+        let Some((pattern, flags)) = &self.filter else {
+            return Some(files);
+        };
+        let keys: Vec<String> = files
+            .iter()
+            .map(|file| {
+                let relative = file.strip_prefix(&directory).unwrap_or(file);
+                format!("./{}", relative.to_string_lossy().replace('\\', "/"))
+            })
+            .collect();
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+        // A filter the matcher can't handle includes every file, erring on the
+        // side of running too many tests.
+        if let Some(matches) = regexp_matches(pattern, flags, &keys) {
+            let mut matches = matches.into_iter();
+            files.retain(|_| matches.next().unwrap_or(true));
+        }
+        Some(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        env::current_dir().unwrap().join("fixtures/require")
+    }
+
+    fn context(directory: &str, recursive: bool, filter: Option<&str>) -> RequireContext {
+        RequireContext {
+            directory: directory.to_string(),
+            recursive,
+            filter: filter.map(|f| (f.to_string(), String::new())),
+        }
+    }
+
+    fn names(files: Vec<PathBuf>) -> Vec<String> {
+        files
+            .iter()
+            .map(|f| {
+                f.strip_prefix(fixtures())
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_filter() {
+        let files = context("./", true, Some(r"\.spec\.js$")).files(&fixtures());
+        assert_eq!(names(files.unwrap()), vec!["suite.spec.js"]);
+    }
+
+    #[test]
+    fn test_no_filter() {
+        let files = context(".", false, None).files(&fixtures());
+        assert_eq!(
+            names(files.unwrap()),
+            vec!["context.js", "module.js", "suite.spec.js"]
+        );
+    }
+
+    #[test]
+    fn test_missing_directory() {
+        assert_eq!(context("./missing", true, None).files(&fixtures()), None);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{borrow::Cow, env, fmt, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// A byte range in a source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorSpan {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// An error found while building the import graph. `file` is relative to
+/// the current directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AffectedError {
+    /// The file has a syntax error. Imports before the error are still
+    /// collected.
+    Parse {
+        file: String,
+        message: String,
+        span: Option<ErrorSpan>,
+    },
+    /// An `import()` or `import.meta.resolve()` call, named by `call`, whose
+    /// argument isn't a string literal.
+    UnsupportedDynamicImport {
+        file: String,
+        span: ErrorSpan,
+        call: String,
+    },
+    /// A `require()`, `require.resolve()`, `require.context()`, require alias
+    /// or import rule call, named by `call`, without the `expected`
+    /// arguments.
+    InvalidRequireArguments {
+        file: String,
+        span: ErrorSpan,
+        call: String,
+        expected: String,
+    },
+    /// An `import.meta.glob()` call whose patterns aren't string literals
+    /// starting with `./`, `../` or `/`.
+    InvalidGlobArguments { file: String, span: ErrorSpan },
+    /// `specifier` couldn't be resolved from `file`.
+    Resolve {
+        file: String,
+        specifier: String,
+        message: String,
+    },
+    /// The file couldn't be read.
+    UnreadableFile { file: String, message: String },
+}
+
+impl AffectedError {
+    pub fn file(&self) -> &str {
+        match self {
+            AffectedError::Parse { file, .. }
+            | AffectedError::UnsupportedDynamicImport { file, .. }
+            | AffectedError::InvalidRequireArguments { file, .. }
+            | AffectedError::InvalidGlobArguments { file, .. }
+            | AffectedError::Resolve { file, .. }
+            | AffectedError::UnreadableFile { file, .. } => file,
+        }
+    }
+
+    pub fn span(&self) -> Option<ErrorSpan> {
+        match self {
+            AffectedError::Parse { span, .. } => *span,
+            AffectedError::UnsupportedDynamicImport { span, .. }
+            | AffectedError::InvalidRequireArguments { span, .. }
+            | AffectedError::InvalidGlobArguments { span, .. } => Some(*span),
+            AffectedError::Resolve { .. } | AffectedError::UnreadableFile { .. } => None,
+        }
+    }
+
+    pub fn specifier(&self) -> Option<&str> {
+        match self {
+            AffectedError::Resolve { specifier, .. } => Some(specifier),
+            _ => None,
+        }
+    }
+
+    /// Moves the span `offset` bytes forward, for errors found in a part of
+    /// a file that was parsed on its own.
+    pub fn shift_span(&mut self, offset: u32) {
+        match self {
+            AffectedError::Parse {
+                span: Some(span), ..
+            }
+            | AffectedError::UnsupportedDynamicImport { span, .. }
+            | AffectedError::InvalidRequireArguments { span, .. }
+            | AffectedError::InvalidGlobArguments { span, .. } => {
+                span.start += offset;
+                span.end += offset;
+            }
+            AffectedError::Parse { span: None, .. }
+            | AffectedError::Resolve { .. }
+            | AffectedError::UnreadableFile { .. } => {}
+        }
+    }
+
+    /// The error message without the file name.
+    pub fn message(&self) -> Cow<'_, str> {
+        match self {
+            AffectedError::Parse { message, .. }
+            | AffectedError::Resolve { message, .. }
+            | AffectedError::UnreadableFile { message, .. } => Cow::Borrowed(message),
+            AffectedError::UnsupportedDynamicImport { call, .. } => Cow::Owned(format!(
+                "{call}() must have a string literal argument without dynamic parts"
+            )),
+            AffectedError::InvalidRequireArguments { call, expected, .. } => {
+                Cow::Owned(format!("{call}() must have {expected}"))
+            }
+            AffectedError::InvalidGlobArguments { .. } => Cow::Borrowed(
+                "Glob import patterns must be string literals starting with './', '../' or '/'",
+            ),
+        }
+    }
+}
+
+impl fmt::Display for AffectedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}]\n{}", self.file(), self.message())
+    }
+}
+
+impl std::error::Error for AffectedError {}
+
+/// Invalid input passed to sovra. The `try_` variants of the entry points
+/// return these; the others panic with the same message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputError {
+    /// A `changes` entry that can't be parsed.
+    InvalidChangedEntry { entry: String, reason: String },
+    /// A require alias that isn't `"name"` or `"object.method"`.
+    InvalidRequireAlias { alias: String, reason: String },
+    /// An import rule with an empty callee or property segment.
+    InvalidImportRule { callee: String, reason: String },
+    /// The current directory can't be read, like when it has been removed.
+    UnreadableCurrentDir { message: String },
+}
+
+/// [`env::current_dir`], failing with an [`InputError`].
+pub fn current_dir() -> Result<PathBuf, InputError> {
+    env::current_dir().map_err(|e| InputError::UnreadableCurrentDir {
+        message: e.to_string(),
+    })
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::InvalidChangedEntry { entry, reason } => {
+                write!(f, "Invalid changeset entry '{entry}': {reason}")
+            }
+            InputError::InvalidRequireAlias { alias, reason } => {
+                write!(f, "Invalid require alias '{alias}': {reason}")
+            }
+            InputError::InvalidImportRule { callee, reason } => {
+                write!(f, "Invalid import rule '{callee}': {reason}")
+            }
+            InputError::UnreadableCurrentDir { message } => {
+                write!(f, "Cannot read the current directory: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let error = AffectedError::Resolve {
+            file: "src/app.js".to_string(),
+            specifier: "hest".to_string(),
+            message: "Cannot find module 'hest'".to_string(),
+        };
+        assert_eq!(error.to_string(), "[src/app.js]\nCannot find module 'hest'");
+        assert_eq!(error.specifier(), Some("hest"));
+        assert_eq!(error.span(), None);
+
+        let error = AffectedError::InvalidRequireArguments {
+            file: "src/app.js".to_string(),
+            span: ErrorSpan { start: 0, end: 10 },
+            call: "require".to_string(),
+            expected: "a single string literal argument".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "[src/app.js]\nrequire() must have a single string literal argument"
+        );
+        assert_eq!(error.span(), Some(ErrorSpan { start: 0, end: 10 }));
+
+        let error = InputError::UnreadableCurrentDir {
+            message: "No such file or directory".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Cannot read the current directory: No such file or directory"
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::changeset::{normalize, ChangeKind, ChangedEntry};
+
+/// Which changes of a git repository to collect with [`git_changes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitChanges {
+    /// Files committed on `HEAD` since it forked from the base ref, like
+    /// `git diff base...HEAD`.
+    Since(String),
+    /// Like [`GitChanges::Since`], plus everything that isn't committed yet.
+    SinceIncludingUncommitted(String),
+    /// Changes added to the index.
+    Staged,
+    /// Changes to tracked files that aren't added to the index.
+    Unstaged,
+    /// Files git doesn't track and doesn't ignore.
+    Untracked,
+    /// Staged, unstaged and untracked changes together.
+    Uncommitted,
+}
+
+fn git(current_dir: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(current_dir)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim(),
+        )));
+    }
+    String::from_utf8(output.stdout).map_err(io::Error::other)
+}
+
+/// Splits the NUL separated paths printed by `git ... -z`.
+fn split_paths(output: &str) -> impl Iterator<Item = &str> {
+    output.split('\0').filter(|p| !p.is_empty())
+}
+
+/// Runs `git diff --name-status` with `args` and parses its output, with
+/// paths relative to the repository `root`.
+fn diff(current_dir: &Path, root: &Path, args: &[&str]) -> io::Result<Vec<ChangedEntry>> {
+    let mut diff_args = vec!["diff", "--name-status", "--find-renames", "-z"];
+    diff_args.extend_from_slice(args);
+    let output = git(current_dir, &diff_args)?;
+
+    let mut entries = Vec::new();
+    let mut fields = split_paths(&output);
+    while let Some(status) = fields.next() {
+        let mut path = || {
+            fields
+                .next()
+                .map(|p| root.join(p))
+                .ok_or_else(|| io::Error::other(format!("Unexpected `git diff` output: {output}")))
+        };
+        let entry = match status.chars().next() {
+            Some('A') | Some('C') => ChangedEntry::File {
+                // Copies carry the source path first, which didn't change.
+                path: if status.starts_with('C') {
+                    path()?;
+                    path()?
+                } else {
+                    path()?
+                },
+                kind: ChangeKind::Added,
+            },
+            Some('D') => ChangedEntry::File {
+                path: path()?,
+                kind: ChangeKind::Deleted,
+            },
+            Some('R') => {
+                let from = path()?;
+                ChangedEntry::File {
+                    path: path()?,
+                    kind: ChangeKind::Renamed { from },
+                }
+            }
+            _ => ChangedEntry::modified(path()?),
+        };
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn untracked(current_dir: &Path, root: &Path) -> io::Result<Vec<ChangedEntry>> {
+    let output = git(
+        current_dir,
+        &[
+            "ls-files",
+            "--others",
+            "--exclude-standard",
+            "--full-name",
+            "-z",
+        ],
+    )?;
+    Ok(split_paths(&output)
+        .map(|p| ChangedEntry::File {
+            path: root.join(p),
+            kind: ChangeKind::Added,
+        })
+        .collect())
+}
+
+fn merge_base(current_dir: &Path, base: &str) -> io::Result<String> {
+    // A base starting with `-` is still a ref, not an option.
+    Ok(git(
+        current_dir,
+        &["merge-base", "--end-of-options", base, "HEAD"],
+    )?
+    .trim()
+    .to_string())
+}
+
+fn has_head(current_dir: &Path) -> bool {
+    git(current_dir, &["rev-parse", "--verify", "-q", "HEAD"]).is_ok()
+}
+
+/// Lists the files changed in the git repository containing `current_dir`.
+/// Paths are made absolute from the repository root as reached from
+/// `current_dir`, rather than its physical path, so they match the
+/// [`ChangedEntry::File`] values parsed from change entries relative to
+/// `current_dir` even through symlinks. Deleted and renamed files are
+/// included with their [`ChangeKind`].
+pub fn git_changes(current_dir: &Path, changes: &GitChanges) -> io::Result<Vec<ChangedEntry>> {
+    let cdup = git(current_dir, &["rev-parse", "--show-cdup"])?;
+    let root = normalize(&current_dir.join(cdup.trim_end_matches('\n')));
+    let root = root.as_path();
+    let entries = match changes {
+        GitChanges::Since(base) => {
+            let merge_base = merge_base(current_dir, base)?;
+            diff(current_dir, root, &[&merge_base, "HEAD"])?
+        }
+        GitChanges::SinceIncludingUncommitted(base) => {
+            let merge_base = merge_base(current_dir, base)?;
+            let mut entries = diff(current_dir, root, &[&merge_base])?;
+            entries.extend(untracked(current_dir, root)?);
+            entries
+        }
+        GitChanges::Staged => diff(current_dir, root, &["--cached"])?,
+        GitChanges::Unstaged => diff(current_dir, root, &[])?,
+        GitChanges::Untracked => untracked(current_dir, root)?,
+        GitChanges::Uncommitted if has_head(current_dir) => {
+            let mut entries = diff(current_dir, root, &["HEAD"])?;
+            entries.extend(untracked(current_dir, root)?);
+            entries
+        }
+        GitChanges::Uncommitted => {
+            // Without commits there's no `HEAD` to diff against, so staged
+            // and unstaged changes are listed separately.
+            let mut entries = diff(current_dir, root, &["--cached"])?;
+            let path = |entry: &ChangedEntry| match entry {
+                ChangedEntry::File { path, .. } => Some(path.clone()),
+                ChangedEntry::Package(_) => None,
+            };
+            let staged: Vec<Option<PathBuf>> = entries.iter().map(path).collect();
+            let unstaged: Vec<ChangedEntry> = diff(current_dir, root, &[])?
+                .into_iter()
+                .filter(|entry| !staged.contains(&path(entry)))
+                .collect();
+            entries.extend(unstaged);
+            entries.extend(untracked(current_dir, root)?);
+            entries
+        }
+    };
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    struct Repo {
+        dir: PathBuf,
+    }
+
+    impl Repo {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("sovra-git-{name}-{}", process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let repo = Repo { dir };
+            repo.git(&["init", "-q", "-b", "main"]);
+            repo
+        }
+
+        fn git(&self, args: &[&str]) {
+            let mut all_args = vec![
+                "-c",
+                "user.name=sovra",
+                "-c",
+                "user.email=sovra@example.com",
+            ];
+            all_args.extend_from_slice(args);
+            git(&self.dir, &all_args).unwrap();
+        }
+
+        fn write(&self, path: &str, contents: &str) {
+            let path = self.dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        fn commit(&self, message: &str) {
+            self.git(&["add", "-A"]);
+            self.git(&["commit", "-q", "-m", message]);
+        }
+
+        fn changes(&self, changes: GitChanges) -> Vec<PathBuf> {
+            let mut paths: Vec<PathBuf> = git_changes(&self.dir, &changes)
+                .unwrap()
+                .into_iter()
+                .map(|entry| match entry {
+                    ChangedEntry::File { path, .. } => {
+                        path.strip_prefix(&self.dir).unwrap().to_path_buf()
+                    }
+                    ChangedEntry::Package(_) => unreachable!(),
+                })
+                .collect();
+            paths.sort();
+            paths
+        }
+
+        fn remove(self) {
+            fs::remove_dir_all(&self.dir).unwrap();
+        }
+    }
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn test_since_uses_merge_base() {
+        let repo = Repo::new("since");
+        repo.write("a.js", "");
+        repo.write("b.js", "");
+        repo.commit("initial");
+        repo.git(&["checkout", "-q", "-b", "feature"]);
+        repo.write("src/c.js", "");
+        repo.commit("feature");
+        // Commits on the base branch after the fork point are not changes.
+        repo.git(&["checkout", "-q", "main"]);
+        repo.write("a.js", "changed on main");
+        repo.commit("main");
+        repo.git(&["checkout", "-q", "feature"]);
+        repo.write("b.js", "uncommitted");
+
+        assert_eq!(
+            repo.changes(GitChanges::Since("main".to_string())),
+            paths(&["src/c.js"])
+        );
+        assert_eq!(
+            repo.changes(GitChanges::SinceIncludingUncommitted("main".to_string())),
+            paths(&["b.js", "src/c.js"])
+        );
+        repo.remove();
+    }
+
+    #[test]
+    fn test_working_tree_modes() {
+        let repo = Repo::new("working-tree");
+        repo.write("staged.js", "");
+        repo.write("unstaged.js", "");
+        repo.commit("initial");
+        repo.write("staged.js", "staged");
+        repo.git(&["add", "staged.js"]);
+        repo.write("unstaged.js", "unstaged");
+        repo.write("untracked.js", "");
+        repo.write(".gitignore", "ignored.js\n");
+        repo.write("ignored.js", "");
+
+        assert_eq!(repo.changes(GitChanges::Staged), paths(&["staged.js"]));
+        assert_eq!(repo.changes(GitChanges::Unstaged), paths(&["unstaged.js"]));
+        assert_eq!(
+            repo.changes(GitChanges::Untracked),
+            paths(&[".gitignore", "untracked.js"])
+        );
+        assert_eq!(
+            repo.changes(GitChanges::Uncommitted),
+            paths(&[".gitignore", "staged.js", "unstaged.js", "untracked.js"])
+        );
+        repo.remove();
+    }
+
+    #[test]
+    fn test_change_kinds() {
+        let repo = Repo::new("kinds");
+        repo.write("old.js", "export const x = 1;");
+        // Distinct contents, so git doesn't pair them up as renames.
+        repo.write("deleted.js", "export const deleted = 1;");
+        repo.write("modified.js", "export const modified = 1;");
+        repo.commit("initial");
+        repo.git(&["mv", "old.js", "new.js"]);
+        repo.git(&["rm", "-q", "deleted.js"]);
+        repo.write("modified.js", "changed");
+        repo.write("added.js", "export const added = 1;");
+        repo.git(&["add", "-A"]);
+
+        let mut entries = git_changes(&repo.dir, &GitChanges::Staged).unwrap();
+        entries.sort_by_key(|entry| match entry {
+            ChangedEntry::File { path, .. } => path.clone(),
+            ChangedEntry::Package(_) => unreachable!(),
+        });
+        assert_eq!(
+            entries,
+            vec![
+                ChangedEntry::File {
+                    path: repo.dir.join("added.js"),
+                    kind: ChangeKind::Added,
+                },
+                ChangedEntry::File {
+                    path: repo.dir.join("deleted.js"),
+                    kind: ChangeKind::Deleted,
+                },
+                ChangedEntry::modified(repo.dir.join("modified.js")),
+                ChangedEntry::File {
+                    path: repo.dir.join("new.js"),
+                    kind: ChangeKind::Renamed {
+                        from: repo.dir.join("old.js"),
+                    },
+                },
+            ]
+        );
+        repo.remove();
+    }
+
+    #[test]
+    fn test_paths_are_absolute_from_a_subdirectory() {
+        let repo = Repo::new("subdir");
+        repo.write("pkg/a.js", "");
+        repo.commit("initial");
+        repo.write("pkg/a.js", "changed");
+        repo.write("pkg/untracked.js", "");
+
+        let entries = git_changes(&repo.dir.join("pkg"), &GitChanges::Uncommitted).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ChangedEntry::modified(repo.dir.join("pkg/a.js")),
+                ChangedEntry::File {
+                    path: repo.dir.join("pkg/untracked.js"),
+                    kind: ChangeKind::Added,
+                },
+            ]
+        );
+        repo.remove();
+    }
+
+    #[test]
+    fn test_uncommitted_without_commits() {
+        let repo = Repo::new("no-commits");
+        repo.write("staged.js", "");
+        repo.git(&["add", "staged.js"]);
+        repo.write("staged.js", "modified after staging");
+        repo.write("untracked.js", "");
+        assert_eq!(
+            repo.changes(GitChanges::Uncommitted),
+            paths(&["staged.js", "untracked.js"])
+        );
+        repo.remove();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_paths_follow_symlinked_checkout() {
+        let repo = Repo::new("symlinked");
+        repo.write("pkg/a.js", "");
+        repo.commit("initial");
+        repo.write("pkg/a.js", "changed");
+        let link = repo.dir.with_extension("link");
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink(&repo.dir, &link).unwrap();
+
+        let entries = git_changes(&link.join("pkg"), &GitChanges::Unstaged).unwrap();
+        assert_eq!(entries, vec![ChangedEntry::modified(link.join("pkg/a.js"))]);
+        fs::remove_file(link).unwrap();
+        repo.remove();
+    }
+
+    #[test]
+    fn test_unknown_base_errors() {
+        let repo = Repo::new("unknown-base");
+        repo.write("a.js", "");
+        repo.commit("initial");
+        let err = git_changes(&repo.dir, &GitChanges::Since("nope".to_string())).unwrap_err();
+        assert!(err.to_string().contains("merge-base"));
+        repo.remove();
+    }
+
+    #[test]
+    fn test_base_is_not_an_option() {
+        let repo = Repo::new("option-base");
+        repo.write("a.js", "");
+        repo.commit("initial");
+        let err = git_changes(&repo.dir, &GitChanges::Since("--octopus".to_string())).unwrap_err();
+        assert!(err.to_string().contains("merge-base"));
+        repo.remove();
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::changeset::normalize;
+
+/// A Vite `import.meta.glob(patterns, options)` call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportGlob {
+    /// Patterns relative to the importing file (`./`, `../`) or to the
+    /// project root (`/`). Patterns starting with `!` exclude files.
+    pub patterns: Vec<String>,
+    /// Whether `node_modules` and dot folders are searched too, like Vite's
+    /// `exhaustive` option.
+    pub exhaustive: bool,
+}
+
+fn is_glob_component(component: &str) -> bool {
+    component.contains(['*', '?', '[', '{'])
+}
+
+/// Escapes `text` so it only matches itself in a glob.
+pub fn escape_glob(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '{' | '}' | '!' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+pub fn to_slash(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+impl ImportGlob {
+    /// Lists the files matched by the patterns, sorted. `importer` is the
+    /// file calling `import.meta.glob`, which is never included, and `root`
+    /// the directory `/` patterns are relative to.
+    pub fn files(&self, importer: &Path, root: &Path) -> Vec<PathBuf> {
+        let base = importer.parent().unwrap_or(root);
+        let absolute = |pattern: &str| match pattern.strip_prefix('/') {
+            Some(pattern) => normalize(&root.join(pattern)),
+            None => normalize(&base.join(pattern)),
+        };
+        let (negative, positive): (Vec<&String>, Vec<&String>) =
+            self.patterns.iter().partition(|p| p.starts_with('!'));
+        let negative: Vec<String> = negative
+            .into_iter()
+            .map(|pattern| to_slash(&absolute(&pattern[1..])))
+            .collect();
+
+        let mut files = BTreeSet::new();
+        for pattern in positive {
+            let pattern = absolute(pattern);
+            // Only the part of the tree below the pattern's static prefix
+            // needs to be walked. The last component names files, so a
+            // pattern without wildcards walks its parent.
+            let directory = static_prefix(pattern.parent().unwrap_or(&pattern));
+            let pattern = to_slash(&pattern);
+            walk_files(&directory, self.exhaustive, &mut |file| {
+                let path = to_slash(file);
+                fast_glob::glob_match(&pattern, &path)
+                    && !negative.iter().any(|n| fast_glob::glob_match(n, &path))
+                    && file != importer
+            })
+            .into_iter()
+            .for_each(|file| {
+                files.insert(file);
+            });
+        }
+        files.into_iter().collect()
+    }
+}
+
+/// The leading components of `pattern` without wildcards.
+pub fn static_prefix(pattern: &Path) -> PathBuf {
+    pattern
+        .components()
+        .take_while(|c| !is_glob_component(&c.as_os_str().to_string_lossy()))
+        .collect()
+}
+
+/// Lists the files below `directory` that `matches` accepts. `node_modules`
+/// and dot folders are skipped unless `exhaustive` is set.
+pub fn walk_files(
+    directory: &Path,
+    exhaustive: bool,
+    matches: &mut dyn FnMut(&Path) -> bool,
+) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack = vec![directory.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                if exhaustive || !(name == "node_modules" || name.starts_with('.')) {
+                    stack.push(path);
+                }
+            } else if matches(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+/// True if `pattern` is relative or absolute, optionally negated. Vite also
+/// accepts aliased patterns, which sovra can't expand.
+pub fn is_supported_glob(pattern: &str) -> bool {
+    let pattern = pattern.strip_prefix('!').unwrap_or(pattern);
+    matches!(
+        Path::new(pattern).components().next(),
+        Some(Component::CurDir | Component::ParentDir | Component::RootDir)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        env::current_dir().unwrap().join("fixtures/glob")
+    }
+
+    fn files(patterns: Vec<&str>, exhaustive: bool) -> Vec<String> {
+        let glob = ImportGlob {
+            patterns: patterns.into_iter().map(str::to_string).collect(),
+            exhaustive,
+        };
+        glob.files(&fixtures().join("router.js"), &fixtures())
+            .iter()
+            .map(|f| to_slash(f.strip_prefix(fixtures()).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_relative_pattern() {
+        assert_eq!(
+            files(vec!["./pages/*.js"], false),
+            vec!["pages/about.js", "pages/home.js"]
+        );
+        assert_eq!(
+            files(vec!["./pages/**/*.js"], false),
+            vec!["pages/about.js", "pages/admin/users.js", "pages/home.js"]
+        );
+    }
+
+    #[test]
+    fn test_negated_pattern() {
+        assert_eq!(
+            files(vec!["./pages/**/*.js", "!./pages/admin/**"], false),
+            vec!["pages/about.js", "pages/home.js"]
+        );
+    }
+
+    #[test]
+    fn test_root_pattern_and_importer() {
+        assert_eq!(
+            files(vec!["/locales/*.json"], false),
+            vec!["locales/en.json", "locales/sv.json"]
+        );
+        assert_eq!(files(vec!["./*.js"], false), vec!["router.spec.js"]);
+    }
+
+    #[test]
+    fn test_pattern_without_wildcards() {
+        assert_eq!(files(vec!["./pages/home.js"], false), vec!["pages/home.js"]);
+        assert!(files(vec!["./pages/missing.js"], false).is_empty());
+    }
+
+    #[test]
+    fn test_escape_glob() {
+        assert_eq!(escape_glob("./[id]/*.js"), r"./\[id\]/\*.js");
+        assert!(fast_glob::glob_match(escape_glob("[id].js"), "[id].js"));
+        assert!(!fast_glob::glob_match(escape_glob("[id].js"), "i.js"));
+    }
+
+    #[test]
+    fn test_supported_glob() {
+        assert!(is_supported_glob("./pages/*.js"));
+        assert!(is_supported_glob("../pages/*.js"));
+        assert!(is_supported_glob("/src/*.js"));
+        assert!(is_supported_glob("!./pages/admin.js"));
+        assert!(!is_supported_glob("@/pages/*.js"));
+        assert!(!is_supported_glob("pages/*.js"));
+    }
+}
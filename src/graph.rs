@@ -0,0 +1,1378 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use oxc_resolver::{ResolveError, Resolver};
+use oxc_span::SourceType;
+use rayon::prelude::*;
+
+use crate::affected::{AffectedOptions, AffectedReason, AffectedReturn, Explanation};
+use crate::ambient::{resolve_types_reference, AmbientChange, AmbientTypes};
+use crate::cache::{CachedImport, CachedScan, ScanCache};
+use crate::changeset::{
+    bare_specifier_segments, matching_changed_package, node_modules_segments, normalize,
+    parse_changed_entry, specifier_targets_removed, ChangeKind, ChangedEntry,
+};
+use crate::error::{current_dir, AffectedError, InputError};
+use crate::glob::to_slash;
+use crate::graphql::{collect_graphql_imports, is_graphql};
+use crate::hook::{HookResolution, ResolveHook, ResolveHookStage};
+use crate::implicit::ImplicitDependency;
+use crate::imports::{self, CollectOptions, ImportUsage, ImportsReturn, RequireAlias};
+use crate::rules::ImportRule;
+use crate::sfc::{collect_sfc_imports, SfcKind};
+use crate::styles::{collect_style_imports, resolve_style_import, StyleSyntax};
+
+enum ScanEdge {
+    /// Resolved to a regular file; `is_in_node_modules` is precomputed to
+    /// avoid touching `module_paths` again when querying the graph.
+    Resolved {
+        import: PathBuf,
+        is_in_node_modules: bool,
+        usage: ImportUsage,
+    },
+    /// Resolve failed. Whether this is an error or a match against a changed
+    /// npm package depends on the changeset, so it's decided per query.
+    /// Unresolved assets have no error, but still match removed files.
+    Unresolved {
+        specifier: String,
+        error: Option<AffectedError>,
+        retry: Retry,
+    },
+}
+
+/// What an unresolved edge is checked against when polling for created files.
+enum Retry {
+    Import(CachedImport),
+    /// The directory of a `require.context` call.
+    Directory(PathBuf),
+}
+
+struct FileScan {
+    absolute_path: PathBuf,
+    parser_errors: Vec<AffectedError>,
+    edges: Vec<ScanEdge>,
+    /// Names exported by the file's own declarations, when tracking symbols.
+    exports: BTreeSet<String>,
+}
+
+/// The exports of a file that are affected by a changeset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AffectedExports {
+    All,
+    Names(BTreeSet<String>),
+}
+
+impl AffectedExports {
+    /// Adds `other`, returning whether anything new was added.
+    fn extend(&mut self, other: AffectedExports) -> bool {
+        match (&mut *self, other) {
+            (AffectedExports::All, _) => false,
+            (_, AffectedExports::All) => {
+                *self = AffectedExports::All;
+                true
+            }
+            (AffectedExports::Names(names), AffectedExports::Names(other)) => {
+                let len = names.len();
+                names.extend(other);
+                names.len() != len
+            }
+        }
+    }
+}
+
+/// Affected files, and for each one the file it was reached from.
+type Propagation<'a> = (HashSet<&'a PathBuf>, HashMap<&'a PathBuf, &'a PathBuf>);
+
+/// The options that change what scanning a file returns.
+struct ScanOptions {
+    ignore_type_imports: bool,
+    require_aliases: Vec<RequireAlias>,
+    import_rules: Vec<ImportRule>,
+    track_symbols: bool,
+    expand_dynamic_imports: bool,
+    resolve_hook: Option<ResolveHook>,
+    implicit_dependencies: Vec<ImplicitDependency>,
+}
+
+/// The kinds of files imports are collected from.
+#[derive(Clone, Copy)]
+enum FileKind {
+    Module(SourceType),
+    Component(SfcKind),
+    Stylesheet(StyleSyntax),
+    GraphQl,
+}
+
+impl FileKind {
+    fn from_path(path: &Path) -> Option<Self> {
+        SourceType::from_path(path)
+            .ok()
+            .map(FileKind::Module)
+            .or_else(|| SfcKind::from_path(path).map(FileKind::Component))
+            .or_else(|| StyleSyntax::from_path(path).map(FileKind::Stylesheet))
+            .or_else(|| is_graphql(path).then_some(FileKind::GraphQl))
+    }
+}
+
+fn collect_scan(
+    kind: FileKind,
+    absolute_path: &PathBuf,
+    source_text: &str,
+    options: &ScanOptions,
+) -> CachedScan {
+    let collect = |source_type, text: &str| {
+        imports::collect_imports(
+            source_type,
+            text,
+            Some(absolute_path),
+            &CollectOptions {
+                ignore_type_imports: options.ignore_type_imports,
+                require_aliases: &options.require_aliases,
+                import_rules: &options.import_rules,
+                track_symbols: options.track_symbols,
+                expand_dynamic_imports: options.expand_dynamic_imports,
+            },
+        )
+    };
+    let (result, styles, assets) = match kind {
+        FileKind::Module(source_type) => (collect(source_type, source_text), vec![], vec![]),
+        FileKind::Component(kind) => {
+            let imports = collect_sfc_imports(kind, source_text, collect);
+            (imports.script, imports.styles, imports.assets)
+        }
+        FileKind::GraphQl => (
+            ImportsReturn {
+                imports_paths: collect_graphql_imports(source_text),
+                ..ImportsReturn::default()
+            },
+            vec![],
+            vec![],
+        ),
+        FileKind::Stylesheet(syntax) => (
+            ImportsReturn::default(),
+            collect_style_imports(syntax, source_text)
+                .into_iter()
+                .map(|import| (import, syntax))
+                .collect(),
+            vec![],
+        ),
+    };
+    let mut symbols = result.symbols.unwrap_or_default();
+    let script_imports = result
+        .imports_paths
+        .into_iter()
+        .map(|specifier| CachedImport {
+            usage: symbols
+                .usages
+                .remove(&specifier)
+                .unwrap_or_else(ImportUsage::all),
+            specifier,
+            style: None,
+            asset: false,
+            types: false,
+        });
+    let style_imports = styles.into_iter().map(|(import, syntax)| CachedImport {
+        specifier: import.specifier,
+        usage: ImportUsage::all(),
+        style: Some(syntax),
+        asset: import.asset,
+        types: false,
+    });
+    let asset_imports = assets.into_iter().map(|specifier| CachedImport {
+        specifier,
+        usage: ImportUsage::all(),
+        style: None,
+        asset: true,
+        types: false,
+    });
+    let type_references = result
+        .type_references
+        .into_iter()
+        .map(|specifier| CachedImport {
+            specifier,
+            usage: ImportUsage::all(),
+            style: None,
+            asset: false,
+            types: true,
+        });
+    CachedScan {
+        parser_errors: result.errors,
+        imports: script_imports
+            .chain(style_imports)
+            .chain(asset_imports)
+            .chain(type_references)
+            .collect(),
+        contexts: result.contexts,
+        globs: result.globs,
+        exports: symbols.exports,
+    }
+}
+
+/// How an import specifier resolved.
+enum Resolution {
+    Resolved(PathBuf),
+    Builtin,
+    /// Left out of the graph by the resolve hook.
+    Ignored,
+}
+
+fn resolve_import(
+    import: &CachedImport,
+    resolver: &Resolver,
+    absolute_path: &Path,
+    parent_path: &Path,
+    current_dir: &Path,
+    options: &ScanOptions,
+) -> Result<Resolution, ResolveError> {
+    let hook = |specifier: &str, stage: ResolveHookStage| {
+        let hook = options
+            .resolve_hook
+            .as_ref()
+            .filter(|h| h.stage() == stage)?;
+        Some(match hook.resolve(specifier, absolute_path)? {
+            HookResolution::Path(path) => Resolution::Resolved(normalize(&current_dir.join(path))),
+            HookResolution::Ignore => Resolution::Ignored,
+            HookResolution::Builtin => Resolution::Builtin,
+        })
+    };
+    let specifier = import.specifier.as_str();
+    if let Some(resolution) = hook(specifier, ResolveHookStage::Before) {
+        return Ok(resolution);
+    }
+    let resolution = match import.style {
+        Some(syntax) => resolve_style_import(syntax, resolver, parent_path, specifier),
+        None if import.types => resolve_types_reference(resolver, parent_path, specifier),
+        None => resolver
+            .resolve(parent_path, specifier)
+            .map(|resolution| resolution.into_path_buf()),
+    };
+    match resolution {
+        Ok(path) => Ok(Resolution::Resolved(current_dir.join(path))),
+        Err(ResolveError::Builtin { .. }) => Ok(Resolution::Builtin),
+        Err(e) => hook(specifier, ResolveHookStage::Fallback).ok_or(e),
+    }
+}
+
+fn scan_file(
+    absolute_path: PathBuf,
+    resolver: &Resolver,
+    current_dir: &Path,
+    module_paths: &HashSet<&OsStr>,
+    options: &ScanOptions,
+    cache: Option<&ScanCache>,
+) -> FileScan {
+    // Expanded on every scan, like globs.
+    let mut edges: Vec<ScanEdge> = options
+        .implicit_dependencies
+        .iter()
+        .flat_map(|dependency| dependency.files(&absolute_path, current_dir))
+        .map(|import| ScanEdge::Resolved {
+            import,
+            is_in_node_modules: false,
+            usage: ImportUsage::all(),
+        })
+        .collect();
+
+    let Some(kind) = FileKind::from_path(&absolute_path) else {
+        return FileScan {
+            absolute_path,
+            parser_errors: vec![],
+            edges,
+            exports: BTreeSet::new(),
+        };
+    };
+    let relative_path = absolute_path
+        .strip_prefix(current_dir)
+        .unwrap_or(&absolute_path)
+        .to_string_lossy()
+        .into_owned();
+    let source_text = match fs::read_to_string(&absolute_path) {
+        Ok(source_text) => source_text,
+        Err(e) => {
+            return FileScan {
+                parser_errors: vec![AffectedError::UnreadableFile {
+                    file: relative_path,
+                    message: format!("Cannot read file: {e}"),
+                }],
+                absolute_path,
+                edges,
+                exports: BTreeSet::new(),
+            };
+        }
+    };
+
+    let cached = cache.and_then(|cache| cache.get(&absolute_path, &source_text));
+    let is_cached = cached.is_some();
+    let scan = cached.unwrap_or_else(|| collect_scan(kind, &absolute_path, &source_text, options));
+
+    let Some(parent_path) = absolute_path.parent() else {
+        return FileScan {
+            absolute_path,
+            parser_errors: scan.parser_errors,
+            edges,
+            exports: scan.exports,
+        };
+    };
+
+    edges.reserve(scan.imports.len());
+    for import in scan.imports.iter() {
+        match resolve_import(
+            import,
+            resolver,
+            &absolute_path,
+            parent_path,
+            current_dir,
+            options,
+        ) {
+            Ok(Resolution::Resolved(path)) => {
+                let is_in_node_modules = path
+                    .components()
+                    .any(|c| module_paths.contains(c.as_os_str()));
+                edges.push(ScanEdge::Resolved {
+                    import: path,
+                    is_in_node_modules,
+                    usage: import.usage.clone(),
+                });
+            }
+            Ok(Resolution::Builtin | Resolution::Ignored) => {}
+            Err(e) => edges.push(ScanEdge::Unresolved {
+                specifier: import.specifier.clone(),
+                error: (!import.asset).then(|| AffectedError::Resolve {
+                    file: relative_path.clone(),
+                    specifier: import.specifier.clone(),
+                    message: e.to_string(),
+                }),
+                retry: Retry::Import(import.clone()),
+            }),
+        }
+    }
+
+    // Expanded on every scan, as files may have been added or removed since
+    // the scan was cached.
+    for context in scan.contexts.iter() {
+        match context.files(parent_path) {
+            Some(files) => edges.extend(files.into_iter().map(|import| ScanEdge::Resolved {
+                import,
+                is_in_node_modules: false,
+                usage: ImportUsage::all(),
+            })),
+            None => edges.push(ScanEdge::Unresolved {
+                specifier: context.directory.clone(),
+                error: Some(AffectedError::Resolve {
+                    file: relative_path.clone(),
+                    specifier: context.directory.clone(),
+                    message: format!("Cannot find directory '{}'", context.directory),
+                }),
+                retry: Retry::Directory(parent_path.join(&context.directory)),
+            }),
+        }
+    }
+    for glob in scan.globs.iter() {
+        edges.extend(
+            glob.files(&absolute_path, current_dir)
+                .into_iter()
+                .map(|import| ScanEdge::Resolved {
+                    import,
+                    is_in_node_modules: false,
+                    usage: ImportUsage::all(),
+                }),
+        );
+    }
+
+    if let Some(cache) = cache.filter(|_| !is_cached) {
+        cache.put(&absolute_path, &source_text, scan.clone());
+    }
+    FileScan {
+        absolute_path,
+        parser_errors: scan.parser_errors,
+        edges,
+        exports: scan.exports,
+    }
+}
+
+/// The import graph reachable from a set of test files.
+///
+/// Building the graph parses and resolves every reachable file once; the
+/// graph can then be queried with any number of changesets through
+/// [`DependencyGraph::affected`] without touching the file system again.
+pub struct DependencyGraph {
+    resolver: Resolver,
+    current_dir: PathBuf,
+    scan_options: ScanOptions,
+    explain: bool,
+    cache: Option<ScanCache>,
+    /// Not loaded when type imports are ignored.
+    ambient: Option<AmbientTypes>,
+    test_files: Vec<(String, PathBuf)>,
+    scans: HashMap<PathBuf, FileScan>,
+    dependents_map: HashMap<PathBuf, HashSet<PathBuf>>,
+}
+
+impl DependencyGraph {
+    /// Builds the graph. Panics on invalid [`AffectedOptions::require_aliases`]
+    /// or [`AffectedOptions::import_rules`], see [`DependencyGraph::try_new`].
+    pub fn new(test_files: Vec<&str>, resolver: Resolver, options: &AffectedOptions) -> Self {
+        Self::try_new(test_files, resolver, options).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_new(
+        test_files: Vec<&str>,
+        resolver: Resolver,
+        options: &AffectedOptions,
+    ) -> Result<Self, InputError> {
+        let require_aliases = options
+            .require_aliases
+            .iter()
+            .map(|s| RequireAlias::parse(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        for rule in options.import_rules.iter() {
+            rule.validate()?;
+        }
+        let current_dir = current_dir()?;
+        let mut seen: HashSet<&str> = HashSet::new();
+        let test_files: Vec<(String, PathBuf)> = test_files
+            .into_iter()
+            .filter(|f| seen.insert(f))
+            .map(|f| (f.to_string(), current_dir.join(f)))
+            .collect();
+        let cache = options
+            .cache_dir
+            .as_ref()
+            .map(|dir| ScanCache::new(dir, options));
+        let mut graph = DependencyGraph {
+            resolver,
+            current_dir,
+            scan_options: ScanOptions {
+                ignore_type_imports: options.ignore_type_imports,
+                require_aliases,
+                import_rules: options.import_rules.clone(),
+                track_symbols: options.track_symbols,
+                expand_dynamic_imports: options.expand_dynamic_imports,
+                resolve_hook: options.resolve_hook.clone(),
+                implicit_dependencies: options.implicit_dependencies.clone(),
+            },
+            explain: options.explain,
+            cache,
+            ambient: None,
+            test_files,
+            scans: HashMap::new(),
+            dependents_map: HashMap::new(),
+        };
+        let frontier = graph.test_files.iter().map(|(_, p)| p.clone()).collect();
+        graph.scan(frontier);
+        graph.load_ambient();
+        if let Some(cache) = &graph.cache {
+            cache.prune();
+        }
+        Ok(graph)
+    }
+
+    /// Loads the tsconfigs of the scanned files, which may change as files
+    /// are added to the graph.
+    fn load_ambient(&mut self) {
+        if !self.scan_options.ignore_type_imports {
+            self.ambient = AmbientTypes::load(&self.resolver, self.scans.keys());
+        }
+    }
+
+    /// Scans `frontier` and every file reachable from it that hasn't been
+    /// scanned yet, recording the edges in `dependents_map`.
+    fn scan(&mut self, mut frontier: Vec<PathBuf>) {
+        let module_paths: HashSet<&OsStr> =
+            HashSet::from_iter(self.resolver.options().modules.iter().map(OsStr::new));
+        let mut queued: HashSet<PathBuf> = HashSet::from_iter(frontier.iter().cloned());
+
+        while !frontier.is_empty() {
+            let scans: Vec<FileScan> = frontier
+                .into_par_iter()
+                .map(|path| {
+                    scan_file(
+                        path,
+                        &self.resolver,
+                        &self.current_dir,
+                        &module_paths,
+                        &self.scan_options,
+                        self.cache.as_ref(),
+                    )
+                })
+                .collect();
+
+            let mut next_frontier: Vec<PathBuf> = Vec::new();
+            for scan in scans {
+                for edge in scan.edges.iter() {
+                    let ScanEdge::Resolved {
+                        import,
+                        is_in_node_modules,
+                        ..
+                    } = edge
+                    else {
+                        continue;
+                    };
+                    self.dependents_map
+                        .entry(import.clone())
+                        .or_default()
+                        .insert(scan.absolute_path.clone());
+
+                    // Skip node_modules
+                    if *is_in_node_modules
+                        || self.scans.contains_key(import)
+                        || !queued.insert(import.clone())
+                    {
+                        continue;
+                    }
+                    next_frontier.push(import.clone());
+                }
+                self.scans.insert(scan.absolute_path.clone(), scan);
+            }
+
+            frontier = next_frontier;
+        }
+    }
+
+    /// Re-scans `files` after they were modified on disk, replacing their
+    /// edges in the graph. Newly imported files are scanned, and files that
+    /// are no longer reachable from any test file are dropped. Files that
+    /// aren't part of the graph are ignored.
+    pub fn update_files(&mut self, files: Vec<&str>) {
+        let modified: Vec<PathBuf> = files
+            .iter()
+            .map(|f| self.current_dir.join(f))
+            .filter(|p| self.scans.contains_key(p))
+            .collect();
+        if modified.is_empty() {
+            return;
+        }
+        // The resolver caches what it found on disk, which may have changed.
+        self.resolver.clear_cache();
+        for path in modified.iter() {
+            self.remove_scan(path);
+        }
+        self.scan(modified);
+        self.remove_unreachable();
+        self.load_ambient();
+    }
+
+    /// Re-scans the files with imports that failed to resolve and now
+    /// resolve, as their targets have been created since. Returns the files
+    /// those imports now resolve to.
+    pub fn update_unresolved(&mut self) -> Vec<PathBuf> {
+        // The resolver remembers the files it didn't find, so the unresolved
+        // imports are retried with an empty cache, which is dropped after.
+        let resolver = Resolver::new(self.resolver.options().clone());
+        let importers: Vec<PathBuf> = self
+            .scans
+            .par_iter()
+            .filter(|(path, scan)| {
+                let Some(parent_path) = path.parent() else {
+                    return false;
+                };
+                scan.edges.iter().any(|edge| match edge {
+                    ScanEdge::Unresolved {
+                        retry: Retry::Import(import),
+                        ..
+                    } => resolve_import(
+                        import,
+                        &resolver,
+                        path,
+                        parent_path,
+                        &self.current_dir,
+                        &self.scan_options,
+                    )
+                    .is_ok(),
+                    ScanEdge::Unresolved {
+                        retry: Retry::Directory(directory),
+                        ..
+                    } => directory.is_dir(),
+                    ScanEdge::Resolved { .. } => false,
+                })
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        if importers.is_empty() {
+            return vec![];
+        }
+        let resolved_imports = |graph: &Self| -> HashSet<PathBuf> {
+            importers
+                .iter()
+                .filter_map(|path| graph.scans.get(path))
+                .flat_map(|scan| scan.edges.iter())
+                .filter_map(|edge| match edge {
+                    ScanEdge::Resolved { import, .. } => Some(import.clone()),
+                    ScanEdge::Unresolved { .. } => None,
+                })
+                .collect()
+        };
+        let before = resolved_imports(self);
+        self.update_files(importers.iter().filter_map(|p| p.to_str()).collect());
+        let mut resolved: Vec<PathBuf> = resolved_imports(self)
+            .into_iter()
+            .filter(|import| !before.contains(import))
+            .collect();
+        resolved.sort();
+        resolved
+    }
+
+    /// Every file that has been scanned, i.e. the test files and every file
+    /// reachable from them outside of module folders.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.scans.keys().map(PathBuf::as_path)
+    }
+
+    fn remove_scan(&mut self, path: &Path) {
+        let Some(scan) = self.scans.remove(path) else {
+            return;
+        };
+        for edge in scan.edges {
+            let ScanEdge::Resolved { import, .. } = edge else {
+                continue;
+            };
+            if let Some(dependents) = self.dependents_map.get_mut(&import) {
+                dependents.remove(path);
+                if dependents.is_empty() {
+                    self.dependents_map.remove(&import);
+                }
+            }
+        }
+    }
+
+    fn remove_unreachable(&mut self) {
+        let mut reachable: HashSet<&PathBuf> = self.test_files.iter().map(|(_, p)| p).collect();
+        let mut stack: Vec<&PathBuf> = reachable.iter().copied().collect();
+        while let Some(path) = stack.pop() {
+            let Some(scan) = self.scans.get(path) else {
+                continue;
+            };
+            for edge in scan.edges.iter() {
+                if let ScanEdge::Resolved { import, .. } = edge {
+                    if reachable.insert(import) {
+                        stack.push(import);
+                    }
+                }
+            }
+        }
+        let unreachable: Vec<PathBuf> = self
+            .scans
+            .keys()
+            .filter(|p| !reachable.contains(p))
+            .cloned()
+            .collect();
+        for path in unreachable {
+            self.remove_scan(&path);
+        }
+    }
+
+    /// Returns the test files that have any of `changes` in their import
+    /// graph. See [`crate::collect_affected`] for the accepted entry formats.
+    /// Panics on invalid entries, see [`DependencyGraph::try_affected`].
+    pub fn affected(&self, changes: Vec<&str>) -> AffectedReturn {
+        self.try_affected(changes).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_affected(&self, changes: Vec<&str>) -> Result<AffectedReturn, InputError> {
+        Ok(self.affected_by(self.parse_changes(changes)?))
+    }
+
+    /// Parses changeset entries relative to the graph's working directory.
+    pub(crate) fn parse_changes(
+        &self,
+        changes: Vec<&str>,
+    ) -> Result<Vec<ChangedEntry>, InputError> {
+        changes
+            .into_iter()
+            .map(|entry| parse_changed_entry(entry, &self.current_dir))
+            .collect()
+    }
+
+    /// Like [`DependencyGraph::affected`], with already parsed changes such
+    /// as the ones returned by [`crate::git_changes`].
+    pub fn affected_by(&self, changes: Vec<ChangedEntry>) -> AffectedReturn {
+        let module_paths: HashSet<&str> =
+            HashSet::from_iter(self.resolver.options().modules.iter().map(|m| m.as_str()));
+
+        // Files where an import chain ends, with the reason they're affected.
+        let mut roots: HashMap<PathBuf, AffectedReason> = HashMap::new();
+        // Files that see a changed ambient declaration or types package.
+        let ambient_changes = self
+            .ambient
+            .as_ref()
+            .map(|ambient| ambient.changed_files(&changes, self.scans.keys()))
+            .unwrap_or_default();
+        let mut changed_packages: HashSet<String> = HashSet::new();
+        // Deleted and renamed-away paths, which importers fail to resolve.
+        let mut removed: Vec<PathBuf> = Vec::new();
+        // Added and renamed-to paths, which the graph may not know yet.
+        let mut added: Vec<PathBuf> = Vec::new();
+        for entry in changes {
+            match entry {
+                ChangedEntry::File { path, kind } => {
+                    match kind {
+                        ChangeKind::Modified => {}
+                        ChangeKind::Added => added.push(path.clone()),
+                        ChangeKind::Deleted => removed.push(path.clone()),
+                        ChangeKind::Renamed { from } => {
+                            roots
+                                .entry(from.clone())
+                                .or_insert(AffectedReason::FileChanged);
+                            removed.push(from);
+                            added.push(path.clone());
+                        }
+                    }
+                    roots.entry(path).or_insert(AffectedReason::FileChanged);
+                }
+                ChangedEntry::Package(name) => {
+                    changed_packages.insert(name);
+                }
+            }
+        }
+
+        // Sorted so that errors come out in a stable order.
+        let mut scanned: Vec<&PathBuf> = self.scans.keys().collect();
+        scanned.sort();
+
+        let mut errors: Vec<(&PathBuf, AffectedError)> = Vec::new();
+        for absolute_path in scanned {
+            let scan = &self.scans[absolute_path];
+            errors.extend(
+                scan.parser_errors
+                    .iter()
+                    .map(|e| (absolute_path, e.clone())),
+            );
+            // Removed implicit dependencies aren't on disk anymore and added
+            // ones weren't when the graph was built, so both are matched
+            // against the globs instead.
+            for dependency in self.scan_options.implicit_dependencies.iter() {
+                let Some(pattern) = dependency.pattern(absolute_path, &self.current_dir) else {
+                    continue;
+                };
+                let changed_dependency = removed.iter().chain(added.iter()).find(|changed| {
+                    let relative = changed.strip_prefix(&self.current_dir).unwrap_or(changed);
+                    fast_glob::glob_match(&pattern, to_slash(relative))
+                });
+                if let Some(changed_dependency) = changed_dependency {
+                    roots.entry(absolute_path.clone()).or_insert(
+                        AffectedReason::ImplicitDependencyChanged {
+                            pattern,
+                            path: self.relative_path(changed_dependency),
+                        },
+                    );
+                }
+            }
+            for edge in scan.edges.iter() {
+                match edge {
+                    ScanEdge::Resolved {
+                        import,
+                        is_in_node_modules,
+                        ..
+                    } => {
+                        // Check if any segment-prefix of the package matches
+                        // a changed entry.
+                        if !*is_in_node_modules || changed_packages.is_empty() {
+                            continue;
+                        }
+                        let package = node_modules_segments(import, &module_paths)
+                            .and_then(|s| matching_changed_package(&s, &changed_packages).cloned());
+                        if let Some(package) = package {
+                            roots
+                                .entry(import.clone())
+                                .or_insert(AffectedReason::NodeModulesMatched { package });
+                        }
+                    }
+                    ScanEdge::Unresolved {
+                        specifier, error, ..
+                    } => {
+                        // Fallback: if the resolver couldn't find the module
+                        // on disk (e.g. `node_modules` not installed), match
+                        // the raw specifier against the npm changeset.
+                        let package = if changed_packages.is_empty() {
+                            None
+                        } else {
+                            bare_specifier_segments(specifier).and_then(|s| {
+                                matching_changed_package(&s, &changed_packages).cloned()
+                            })
+                        };
+                        if let Some(package) = package {
+                            roots.entry(absolute_path.clone()).or_insert(
+                                AffectedReason::NpmFallbackMatched {
+                                    specifier: specifier.clone(),
+                                    package,
+                                },
+                            );
+                            continue;
+                        }
+                        // The import broke because the changeset removed its
+                        // target, which affects the importer.
+                        let main_files = &self.resolver.options().main_files;
+                        let removed_target = removed.iter().find(|removed| {
+                            specifier_targets_removed(absolute_path, specifier, removed, main_files)
+                        });
+                        match removed_target {
+                            Some(removed_target) => {
+                                roots.entry(absolute_path.clone()).or_insert(
+                                    AffectedReason::RemovedFileImported {
+                                        specifier: specifier.clone(),
+                                        path: self.relative_path(removed_target),
+                                    },
+                                );
+                            }
+                            None => errors.extend(error.iter().map(|e| (absolute_path, e.clone()))),
+                        }
+                    }
+                }
+            }
+        }
+
+        // Like a search that stops at changed files, only errors in files
+        // reachable from the tests without passing through one are reported.
+        let boundary: HashSet<&PathBuf> = roots
+            .iter()
+            .filter(|(_, reason)| {
+                matches!(
+                    reason,
+                    AffectedReason::FileChanged | AffectedReason::NodeModulesMatched { .. }
+                )
+            })
+            .map(|(path, _)| path)
+            .collect();
+        let reachable = self.reachable_from_tests(&boundary);
+        let errors: Vec<AffectedError> = errors
+            .into_iter()
+            .filter(|(path, _)| reachable.contains(path))
+            .map(|(_, error)| error)
+            .collect();
+
+        let (affected, via) = if self.scan_options.track_symbols {
+            self.propagate_symbols(roots.keys().collect())
+        } else {
+            self.propagate_files(roots.keys().collect())
+        };
+
+        let affected_tests: Vec<&(String, PathBuf)> = self
+            .test_files
+            .iter()
+            .filter(|(_, p)| affected.contains(p))
+            .collect();
+        let explain = |tests: &[&(String, PathBuf)],
+                       via: &HashMap<&PathBuf, &PathBuf>,
+                       reason: &dyn Fn(&PathBuf) -> AffectedReason| {
+            tests
+                .iter()
+                .map(|(file, path)| {
+                    let mut chain = vec![self.relative_path(path)];
+                    let mut current = path;
+                    while let Some(next) = via.get(current) {
+                        chain.push(self.relative_path(next));
+                        current = next;
+                    }
+                    Explanation {
+                        file: file.clone(),
+                        chain,
+                        reason: reason(current),
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut explanations = if self.explain {
+            explain(&affected_tests, &via, &|root| roots[root].clone())
+        } else {
+            vec![]
+        };
+
+        // Every file covered by a tsconfig sees its ambient declarations, so
+        // it and its dependents are affected at the type level.
+        let mut type_tests: Vec<&(String, PathBuf)> = Vec::new();
+        if !ambient_changes.is_empty() {
+            let (type_affected, type_via) =
+                self.propagate_files(ambient_changes.keys().copied().collect());
+            type_tests = self
+                .test_files
+                .iter()
+                .filter(|(_, p)| type_affected.contains(p) && !affected.contains(p))
+                .collect();
+            if self.explain {
+                let reason = |root: &PathBuf| match &ambient_changes[root] {
+                    AmbientChange::Declaration(path) => AffectedReason::AmbientDeclarationChanged {
+                        path: self.relative_path(path),
+                    },
+                    AmbientChange::TypesPackage(package) => AffectedReason::TypesPackageChanged {
+                        package: package.clone(),
+                    },
+                };
+                explanations.extend(explain(&type_tests, &type_via, &reason));
+            }
+        }
+
+        AffectedReturn {
+            errors,
+            files: affected_tests.iter().map(|(f, _)| f.clone()).collect(),
+            type_files: type_tests.iter().map(|(f, _)| f.clone()).collect(),
+            explanations,
+        }
+    }
+
+    /// The files reachable from the test files without passing through
+    /// `boundary`.
+    fn reachable_from_tests(&self, boundary: &HashSet<&PathBuf>) -> HashSet<&PathBuf> {
+        let mut reachable: HashSet<&PathBuf> = HashSet::new();
+        let mut stack: Vec<&PathBuf> = self.test_files.iter().map(|(_, path)| path).collect();
+        while let Some(path) = stack.pop() {
+            if boundary.contains(path) || !reachable.insert(path) {
+                continue;
+            }
+            let Some(scan) = self.scans.get(path) else {
+                continue;
+            };
+            for edge in scan.edges.iter() {
+                if let ScanEdge::Resolved { import, .. } = edge {
+                    stack.push(import);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Finds every file that depends on `roots`. Breadth-first from every
+    /// root at once, so following the returned `via` map from any affected
+    /// file gives a shortest chain back to a root.
+    fn propagate_files<'a>(&'a self, roots: Vec<&'a PathBuf>) -> Propagation<'a> {
+        let mut via: HashMap<&PathBuf, &PathBuf> = HashMap::new();
+        let mut affected: HashSet<&PathBuf> = roots.iter().copied().collect();
+        let mut queue: VecDeque<&PathBuf> = roots.into_iter().collect();
+        while let Some(path) = queue.pop_front() {
+            let Some(dependents) = self.dependents_map.get(path) else {
+                continue;
+            };
+            for dependent in dependents.iter() {
+                if affected.insert(dependent) {
+                    via.insert(dependent, path);
+                    queue.push_back(dependent);
+                }
+            }
+        }
+        (affected, via)
+    }
+
+    /// Like [`DependencyGraph::propagate_files`], but tracks which exports of
+    /// each file are affected, and only follows an import when it uses one of
+    /// them. A file is revisited whenever more of its exports turn out to be
+    /// affected.
+    fn propagate_symbols<'a>(&'a self, roots: Vec<&'a PathBuf>) -> Propagation<'a> {
+        let mut via: HashMap<&PathBuf, &PathBuf> = HashMap::new();
+        let mut affected: HashMap<&PathBuf, AffectedExports> = roots
+            .iter()
+            .map(|root| (*root, AffectedExports::All))
+            .collect();
+        let mut queue: VecDeque<&PathBuf> = roots.into_iter().collect();
+        while let Some(path) = queue.pop_front() {
+            let Some(dependents) = self.dependents_map.get(path) else {
+                continue;
+            };
+            let changed = affected[path].clone();
+            for dependent in dependents.iter() {
+                let Some(scan) = self.scans.get(dependent) else {
+                    continue;
+                };
+                // Several specifiers may resolve to the same file.
+                let mut usage = ImportUsage::default();
+                for edge in scan.edges.iter() {
+                    if let ScanEdge::Resolved {
+                        import, usage: u, ..
+                    } = edge
+                    {
+                        if import == path {
+                            usage.merge(u);
+                        }
+                    }
+                }
+                let Some(exports) = self.affected_exports(path, &changed, &usage) else {
+                    continue;
+                };
+                match affected.get_mut(dependent) {
+                    None => {
+                        affected.insert(dependent, exports);
+                        via.insert(dependent, path);
+                        queue.push_back(dependent);
+                    }
+                    Some(current) => {
+                        if current.extend(exports) {
+                            queue.push_back(dependent);
+                        }
+                    }
+                }
+            }
+        }
+        (affected.into_keys().collect(), via)
+    }
+
+    /// The exports of a file that become affected through its `usage` of
+    /// `path`, whose `changed` exports are affected.
+    fn affected_exports(
+        &self,
+        path: &Path,
+        changed: &AffectedExports,
+        usage: &ImportUsage,
+    ) -> Option<AffectedExports> {
+        let uses_changed = match changed {
+            AffectedExports::All => !usage.names.is_empty(),
+            AffectedExports::Names(names) => usage.names.iter().any(|n| names.contains(n)),
+        };
+        if usage.all || uses_changed {
+            return Some(AffectedExports::All);
+        }
+
+        let mut names: BTreeSet<String> = BTreeSet::new();
+        for (name, exported) in usage.reexports.iter() {
+            let reexports_changed = match changed {
+                AffectedExports::All => true,
+                AffectedExports::Names(names) => name == "*" || names.contains(name),
+            };
+            if reexports_changed {
+                names.insert(exported.clone());
+            }
+        }
+        if usage.reexports_all {
+            // `export *` never re-exports `default`.
+            match changed {
+                AffectedExports::Names(changed) => {
+                    names.extend(changed.iter().filter(|n| *n != "default").cloned());
+                }
+                AffectedExports::All => {
+                    let all = self.export_names(path);
+                    // Exports of CommonJS modules aren't known.
+                    if all.is_empty() {
+                        return Some(AffectedExports::All);
+                    }
+                    names.extend(all.into_iter().filter(|n| n != "default"));
+                }
+            }
+        }
+        (!names.is_empty()).then_some(AffectedExports::Names(names))
+    }
+
+    /// Every name exported by `path`, following `export *` re-exports.
+    fn export_names(&self, path: &Path) -> BTreeSet<String> {
+        let mut names: BTreeSet<String> = BTreeSet::new();
+        let mut visited: HashSet<&Path> = HashSet::new();
+        let mut stack: Vec<&Path> = vec![path];
+        while let Some(path) = stack.pop() {
+            if !visited.insert(path) {
+                continue;
+            }
+            let Some(scan) = self.scans.get(path) else {
+                continue;
+            };
+            names.extend(scan.exports.iter().cloned());
+            for edge in scan.edges.iter() {
+                if let ScanEdge::Resolved { import, usage, .. } = edge {
+                    names.extend(usage.reexports.iter().map(|(_, exported)| exported.clone()));
+                    if usage.reexports_all {
+                        stack.push(import);
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    fn relative_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.current_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        sync::{Arc, Mutex},
+    };
+
+    use oxc_resolver::ResolveOptions;
+
+    use super::*;
+
+    fn graph(test_files: Vec<&str>) -> DependencyGraph {
+        DependencyGraph::new(
+            test_files,
+            Resolver::new(ResolveOptions::default()),
+            &AffectedOptions::default(),
+        )
+    }
+
+    fn sorted(mut files: Vec<String>) -> Vec<String> {
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_graph_queried_with_many_changesets() {
+        let graph = graph(vec![
+            "fixtures/nested/module.spec.js",
+            "fixtures/nested/sub-module.spec.js",
+        ]);
+
+        let ret = graph.affected(vec!["fixtures/nested/module.js"]);
+        assert!(ret.errors.is_empty());
+        assert_eq!(ret.files, vec!["fixtures/nested/module.spec.js"]);
+
+        let ret = graph.affected(vec!["fixtures/nested/sub-module.js"]);
+        assert!(ret.errors.is_empty());
+        assert_eq!(
+            sorted(ret.files),
+            vec![
+                "fixtures/nested/module.spec.js",
+                "fixtures/nested/sub-module.spec.js",
+            ]
+        );
+
+        let ret = graph.affected(vec![]);
+        assert!(ret.errors.is_empty());
+        assert!(ret.files.is_empty());
+    }
+
+    #[test]
+    fn test_graph_affected_by_parsed_entries() {
+        let graph = graph(vec!["fixtures/nested/module.spec.js"]);
+        let module = env::current_dir()
+            .unwrap()
+            .join("fixtures/nested/module.js");
+        let ret = graph.affected_by(vec![ChangedEntry::modified(module)]);
+        assert_eq!(ret.files, vec!["fixtures/nested/module.spec.js"]);
+    }
+
+    #[test]
+    fn test_graph_test_file_changed() {
+        let graph = graph(vec!["fixtures/nested/module.spec.js"]);
+        let ret = graph.affected(vec!["fixtures/nested/module.spec.js"]);
+        assert_eq!(ret.files, vec!["fixtures/nested/module.spec.js"]);
+    }
+
+    #[test]
+    fn test_graph_unresolved_pkg_decided_per_query() {
+        // The same unresolved specifier is a changeset match in one query and
+        // a resolve error in the next.
+        let graph = graph(vec!["fixtures/unresolved-pkg/uses-not-installed.js"]);
+
+        let ret = graph.affected(vec!["npm:not-installed-pkg"]);
+        assert!(ret.errors.is_empty(), "unexpected errors: {:?}", ret.errors);
+        assert_eq!(
+            ret.files,
+            vec!["fixtures/unresolved-pkg/uses-not-installed.js"]
+        );
+
+        let ret = graph.affected(vec!["npm:something-else"]);
+        assert_eq!(ret.errors.len(), 1);
+        assert_eq!(ret.errors[0].specifier(), Some("not-installed-pkg"));
+        assert!(ret.files.is_empty());
+    }
+
+    #[test]
+    fn test_graph_cache_follows_content_changes() {
+        let dir = env::temp_dir().join(format!("sovra-graph-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let spec = dir.join("suite.spec.js");
+        fs::write(dir.join("a.js"), "").unwrap();
+        fs::write(dir.join("b.js"), "").unwrap();
+        fs::write(&spec, "import './a.js';").unwrap();
+
+        let options = AffectedOptions {
+            cache_dir: Some(dir.join(".cache")),
+            ..AffectedOptions::default()
+        };
+        let spec_str = spec.to_str().unwrap();
+        let a = dir.join("a.js");
+        let b = dir.join("b.js");
+        let build = || {
+            DependencyGraph::new(
+                vec![spec_str],
+                Resolver::new(ResolveOptions::default()),
+                &options,
+            )
+        };
+
+        for _ in 0..2 {
+            let graph = build();
+            assert_eq!(
+                graph.affected(vec![a.to_str().unwrap()]).files,
+                vec![spec_str]
+            );
+            assert!(graph.affected(vec![b.to_str().unwrap()]).files.is_empty());
+        }
+        assert!(fs::read_dir(dir.join(".cache")).unwrap().count() > 0);
+
+        fs::write(&spec, "import './b.js';").unwrap();
+        let graph = build();
+        assert!(graph.affected(vec![a.to_str().unwrap()]).files.is_empty());
+        assert_eq!(
+            graph.affected(vec![b.to_str().unwrap()]).files,
+            vec![spec_str]
+        );
+
+        // Imports of cached files are resolved again.
+        fs::remove_file(&b).unwrap();
+        let ret = build().affected(vec![]);
+        assert_eq!(ret.errors.len(), 1);
+        assert_eq!(ret.errors[0].specifier(), Some("./b.js"));
+
+        // Including when a candidate with higher precedence appears.
+        let config = dir.join("config.json");
+        let config_js = dir.join("config.js");
+        fs::write(&config, "{}").unwrap();
+        fs::write(&spec, "import './config';").unwrap();
+        assert_eq!(
+            build().affected(vec![config.to_str().unwrap()]).files,
+            vec![spec_str]
+        );
+        fs::write(&config_js, "").unwrap();
+        let graph = build();
+        assert!(graph
+            .affected(vec![config.to_str().unwrap()])
+            .files
+            .is_empty());
+        assert_eq!(
+            graph.affected(vec![config_js.to_str().unwrap()]).files,
+            vec![spec_str]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_graph_cache_calls_resolve_hook() {
+        let dir = env::temp_dir().join(format!("sovra-graph-hook-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let spec = dir.join("suite.spec.js");
+        let a = dir.join("a.js");
+        fs::write(&a, "").unwrap();
+        fs::write(&spec, "import './a.js';").unwrap();
+
+        let spec_str = spec.to_str().unwrap();
+        let build = |resolution: Option<HookResolution>| {
+            let options = AffectedOptions {
+                cache_dir: Some(dir.join(".cache")),
+                resolve_hook: Some(ResolveHook::new(ResolveHookStage::Before, move |_, _| {
+                    resolution.clone()
+                })),
+                ..AffectedOptions::default()
+            };
+            DependencyGraph::new(
+                vec![spec_str],
+                Resolver::new(ResolveOptions::default()),
+                &options,
+            )
+        };
+
+        let graph = build(None);
+        assert_eq!(
+            graph.affected(vec![a.to_str().unwrap()]).files,
+            vec![spec_str]
+        );
+        // The cached scan is reused, but its imports still go through the hook.
+        let graph = build(Some(HookResolution::Ignore));
+        assert!(graph.affected(vec![a.to_str().unwrap()]).files.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_graph_update_files() {
+        let dir = env::temp_dir().join(format!("sovra-graph-update-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let spec = dir.join("suite.spec.js");
+        let a = dir.join("a.js");
+        let b = dir.join("b.js");
+        fs::write(&a, "import './shared.js';").unwrap();
+        fs::write(&b, "").unwrap();
+        fs::write(dir.join("shared.js"), "").unwrap();
+        fs::write(&spec, "import './a.js';").unwrap();
+
+        let spec_str = spec.to_str().unwrap();
+        let mut graph = graph(vec![spec_str]);
+        assert_eq!(graph.files().count(), 3);
+        assert_eq!(
+            graph.affected(vec![a.to_str().unwrap()]).files,
+            vec![spec_str]
+        );
+
+        fs::write(&spec, "import './b.js';").unwrap();
+        graph.update_files(vec![spec_str]);
+        assert!(graph.affected(vec![a.to_str().unwrap()]).files.is_empty());
+        assert_eq!(
+            graph.affected(vec![b.to_str().unwrap()]).files,
+            vec![spec_str]
+        );
+        // `a.js` and `shared.js` are no longer reachable.
+        let mut files: Vec<&Path> = graph.files().collect();
+        files.sort();
+        assert_eq!(files, vec![b.as_path(), spec.as_path()]);
+
+        // Files outside the graph are ignored.
+        graph.update_files(vec![a.to_str().unwrap()]);
+        assert_eq!(graph.files().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_graph_update_unresolved() {
+        let dir = env::temp_dir().join(format!("sovra-graph-unresolved-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let spec = dir.join("suite.spec.js");
+        let missing = dir.join("missing.js");
+        fs::write(dir.join("a.js"), "").unwrap();
+        fs::write(&spec, "import './a.js'; import './missing.js';").unwrap();
+
+        let resolved = Arc::new(Mutex::new(vec![]));
+        let options = AffectedOptions {
+            resolve_hook: Some(ResolveHook::new(ResolveHookStage::Before, {
+                let resolved = resolved.clone();
+                move |specifier, _| {
+                    resolved.lock().unwrap().push(specifier.to_string());
+                    None
+                }
+            })),
+            ..AffectedOptions::default()
+        };
+        let spec_str = spec.to_str().unwrap();
+        let mut graph = DependencyGraph::new(
+            vec![spec_str],
+            Resolver::new(ResolveOptions::default()),
+            &options,
+        );
+        resolved.lock().unwrap().clear();
+
+        // Only the unresolved import is retried, without re-scanning.
+        assert!(graph.update_unresolved().is_empty());
+        assert_eq!(*resolved.lock().unwrap(), vec!["./missing.js"]);
+
+        fs::write(&missing, "").unwrap();
+        resolved.lock().unwrap().clear();
+        assert_eq!(graph.update_unresolved(), vec![missing.clone()]);
+        assert!(resolved.lock().unwrap().contains(&"./a.js".to_string()));
+        assert_eq!(
+            graph.affected(vec![missing.to_str().unwrap()]).files,
+            vec![spec_str]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_graph_duplicate_test_files() {
+        let graph = graph(vec![
+            "fixtures/nested/module.spec.js",
+            "fixtures/nested/module.spec.js",
+        ]);
+        let ret = graph.affected(vec!["fixtures/nested/module.js"]);
+        assert_eq!(ret.files, vec!["fixtures/nested/module.spec.js"]);
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+
+pub fn is_graphql(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("graphql" | "gql")
+    )
+}
+
+/// Collects the specifiers of `#import "./fragment.graphql"` comments, as
+/// understood by `graphql-tag/loader` and `graphql-import-node`. They
+/// resolve like `require()` calls from the document.
+pub fn collect_graphql_imports(text: &str) -> Vec<String> {
+    let mut specifiers: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let Some(rest) = line.trim_start().strip_prefix("#import") else {
+            continue;
+        };
+        let rest = rest.trim();
+        let Some(quote) = rest.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+            continue;
+        };
+        let Some(end) = rest[1..].find(quote) else {
+            continue;
+        };
+        let specifier = &rest[1..=end];
+        if !specifier.is_empty() && !specifiers.iter().any(|s| s == specifier) {
+            specifiers.push(specifier.to_string());
+        }
+    }
+    specifiers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_imports() {
+        assert_eq!(
+            collect_graphql_imports(
+                r#"#import "./UserFragment.graphql"
+#import './fragments/Avatar.gql'
+  #import "./UserFragment.graphql"
+# #import "./commented.graphql"
+#importer "./not-an-import.graphql"
+query User($id: ID!) {
+  user(id: $id) {
+    ...UserFragment
+  }
+}"#
+            ),
+            vec!["./UserFragment.graphql", "./fragments/Avatar.gql"]
+        );
+    }
+}
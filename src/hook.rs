@@ -0,0 +1,67 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// What a [`ResolveHook`] resolved an import to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookResolution {
+    /// The imported file. Relative paths are relative to the current
+    /// directory.
+    Path(PathBuf),
+    /// The import doesn't refer to a file and is left out of the graph.
+    Ignore,
+    /// The import is a runtime built-in, like `fs`.
+    Builtin,
+}
+
+/// When a [`ResolveHook`] is called.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResolveHookStage {
+    /// Only for imports the resolver fails to resolve.
+    #[default]
+    Fallback,
+    /// Before the resolver, which only runs if the hook returns `None`.
+    Before,
+}
+
+type ResolveCallback = dyn Fn(&str, &Path) -> Option<HookResolution> + Send + Sync;
+
+/// Custom module resolution that can't be expressed as resolver options,
+/// like aliases computed by bundler plugins. Called with the specifier and
+/// the absolute path of the importing file; returning `None` keeps the
+/// resolver's outcome.
+#[derive(Clone)]
+pub struct ResolveHook {
+    callback: Arc<ResolveCallback>,
+    stage: ResolveHookStage,
+}
+
+impl ResolveHook {
+    pub fn new(
+        stage: ResolveHookStage,
+        callback: impl Fn(&str, &Path) -> Option<HookResolution> + Send + Sync + 'static,
+    ) -> Self {
+        ResolveHook {
+            callback: Arc::new(callback),
+            stage,
+        }
+    }
+
+    pub fn stage(&self) -> ResolveHookStage {
+        self.stage
+    }
+
+    pub fn resolve(&self, specifier: &str, importer: &Path) -> Option<HookResolution> {
+        (self.callback)(specifier, importer)
+    }
+}
+
+impl fmt::Debug for ResolveHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResolveHook")
+            .field("stage", &self.stage)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+use crate::glob::{escape_glob, to_slash, ImportGlob};
+
+/// Files that `dependents` depend on without importing them, like fixtures
+/// read with `fs.readFileSync`, golden files or SQL migrations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImplicitDependency {
+    /// Glob matching the dependent files, relative to the current directory.
+    pub dependents: String,
+    /// Glob matching the files each dependent depends on, relative to the
+    /// current directory. `{dir}` is replaced with the dependent's directory
+    /// and `{name}` with its file name, like `{dir}/__fixtures__/**`.
+    pub dependencies: String,
+}
+
+impl ImplicitDependency {
+    /// The glob of `dependent`'s dependencies relative to `current_dir`, or
+    /// `None` if `dependent` isn't matched by [`ImplicitDependency::dependents`].
+    pub fn pattern(&self, dependent: &Path, current_dir: &Path) -> Option<String> {
+        let relative = dependent.strip_prefix(current_dir).ok()?;
+        if !fast_glob::glob_match(&self.dependents, to_slash(relative)) {
+            return None;
+        }
+        let dir = match relative.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => to_slash(dir),
+            _ => ".".to_string(),
+        };
+        let name = relative
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let pattern = self
+            .dependencies
+            .replace("{dir}", &escape_glob(&dir))
+            .replace("{name}", &escape_glob(&name));
+        Some(pattern.trim_start_matches("./").to_string())
+    }
+
+    /// Lists the files `dependent` depends on through this mapping, sorted.
+    pub fn files(&self, dependent: &Path, current_dir: &Path) -> Vec<PathBuf> {
+        let Some(pattern) = self.pattern(dependent, current_dir) else {
+            return vec![];
+        };
+        let glob = ImportGlob {
+            patterns: vec![format!("/{pattern}")],
+            exhaustive: false,
+        };
+        glob.files(dependent, current_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn files(dependency: &ImplicitDependency, dependent: &str) -> Vec<String> {
+        let current_dir = env::current_dir().unwrap();
+        dependency
+            .files(&current_dir.join(dependent), &current_dir)
+            .iter()
+            .map(|f| to_slash(f.strip_prefix(&current_dir).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_dir_template() {
+        let fixtures = ImplicitDependency {
+            dependents: "fixtures/implicit/*.spec.js".to_string(),
+            dependencies: "{dir}/__fixtures__/**".to_string(),
+        };
+        assert_eq!(
+            files(&fixtures, "fixtures/implicit/parser.spec.js"),
+            vec![
+                "fixtures/implicit/__fixtures__/insert.sql",
+                "fixtures/implicit/__fixtures__/select.sql",
+            ]
+        );
+        assert!(files(&fixtures, "fixtures/nested/module.spec.js").is_empty());
+    }
+
+    #[test]
+    fn test_pattern_escapes_paths() {
+        let current_dir = env::current_dir().unwrap();
+        let fixtures = ImplicitDependency {
+            dependents: "fixtures/**/*.spec.js".to_string(),
+            dependencies: "./{dir}/__fixtures__/{name}".to_string(),
+        };
+        assert_eq!(
+            fixtures.pattern(&current_dir.join("fixtures/[id]/a.spec.js"), &current_dir),
+            Some(r"fixtures/\[id\]/__fixtures__/a.spec.js".to_string())
+        );
+        assert_eq!(
+            fixtures.pattern(&current_dir.join("src/a.spec.js"), &current_dir),
+            None
+        );
+    }
+
+    #[test]
+    fn test_name_template() {
+        let snapshots = ImplicitDependency {
+            dependents: "**/*.spec.js".to_string(),
+            dependencies: "{dir}/__snapshots__/{name}.snap".to_string(),
+        };
+        assert_eq!(
+            files(&snapshots, "fixtures/implicit/parser.spec.js"),
+            vec!["fixtures/implicit/__snapshots__/parser.spec.js.snap"]
+        );
+        assert!(files(&snapshots, "fixtures/implicit/schema.spec.js").is_empty());
+    }
+
+    #[test]
+    fn test_static_dependencies() {
+        let migrations = ImplicitDependency {
+            dependents: "fixtures/implicit/schema.spec.js".to_string(),
+            dependencies: "./fixtures/implicit/migrations/*.sql".to_string(),
+        };
+        assert_eq!(
+            files(&migrations, "fixtures/implicit/schema.spec.js"),
+            vec!["fixtures/implicit/migrations/001_users.sql"]
+        );
+    }
+}
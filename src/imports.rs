@@ -1,13 +1,94 @@
-use oxc::diagnostics::{Error, NamedSource, OxcDiagnostic};
+use oxc::diagnostics::OxcDiagnostic;
 use oxc_allocator::Allocator;
 use oxc_ast_visit::{walk, Visit};
 use oxc_parser::Parser;
-use oxc_span::SourceType;
-use std::{collections::HashSet, env, path::PathBuf, sync::Arc};
-
+use oxc_span::{GetSpan, SourceType, Span};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    env,
+    path::PathBuf,
+};
+
+use crate::context::RequireContext;
+use crate::error::{AffectedError, ErrorSpan, InputError};
+use crate::glob::{escape_glob, is_supported_glob, ImportGlob};
+use crate::rules::{callee_segments, ImportRule};
+use crate::sfc::parse_attributes;
+
+#[derive(Default)]
 pub struct ImportsReturn {
-    pub errors: Vec<String>,
+    pub errors: Vec<AffectedError>,
     pub imports_paths: Vec<String>,
+    pub contexts: Vec<RequireContext>,
+    pub globs: Vec<ImportGlob>,
+    /// Packages named by `/// <reference types="..." />` directives, which
+    /// are looked up like TypeScript does rather than as specifiers.
+    pub type_references: Vec<String>,
+    /// Only collected when tracking symbols.
+    pub symbols: Option<ModuleSymbols>,
+}
+
+impl ImportsReturn {
+    /// Adds the imports of another script of the same file.
+    pub fn extend(&mut self, other: ImportsReturn) {
+        self.errors.extend(other.errors);
+        self.imports_paths.extend(other.imports_paths);
+        self.contexts.extend(other.contexts);
+        self.globs.extend(other.globs);
+        self.type_references.extend(other.type_references);
+        match (&mut self.symbols, other.symbols) {
+            (Some(symbols), Some(other)) => {
+                for (specifier, usage) in other.usages {
+                    symbols.usages.entry(specifier).or_default().merge(&usage);
+                }
+                symbols.exports.extend(other.exports);
+            }
+            (symbols @ None, other @ Some(_)) => *symbols = other,
+            (_, None) => {}
+        }
+    }
+}
+
+/// What a file uses from one of its imports.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportUsage {
+    /// The module is used as a whole: side-effect and namespace imports,
+    /// `require()`, `import()` and the like.
+    pub all: bool,
+    /// Imported bindings, `default` for default imports.
+    pub names: BTreeSet<String>,
+    /// `export { name as exported } from` pairs, with `*` as the name for
+    /// `export * as exported from`.
+    pub reexports: BTreeSet<(String, String)>,
+    /// `export * from`.
+    pub reexports_all: bool,
+}
+
+impl ImportUsage {
+    pub fn all() -> Self {
+        ImportUsage {
+            all: true,
+            ..ImportUsage::default()
+        }
+    }
+
+    pub fn merge(&mut self, other: &ImportUsage) {
+        self.all |= other.all;
+        self.names.extend(other.names.iter().cloned());
+        self.reexports.extend(other.reexports.iter().cloned());
+        self.reexports_all |= other.reexports_all;
+    }
+}
+
+/// The bindings a file imports and exports, for tracking changes per
+/// exported symbol through re-exports.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleSymbols {
+    /// Usage per import specifier.
+    pub usages: HashMap<String, ImportUsage>,
+    /// Names exported by declarations in the file itself, not re-exports.
+    pub exports: BTreeSet<String>,
 }
 
 /// A function call that should be collected as if it were a `require()` —
@@ -17,79 +98,301 @@ pub struct ImportsReturn {
 pub struct RequireAlias {
     pub object: Option<String>,
     pub method: String,
+    /// Index of the argument holding the path. Other arguments, like the
+    /// factory passed to `jest.mock('./api', () => ...)`, are ignored.
+    pub argument: usize,
 }
 
 impl RequireAlias {
-    pub fn parse(s: &str) -> Self {
-        assert!(!s.is_empty(), "Require alias must not be empty");
-        let parts: Vec<&str> = s.split('.').collect();
+    /// Parses `"name"` or `"object.method"`, optionally followed by `:` and
+    /// the index of the path argument, like `"loadModule:1"`.
+    pub fn parse(s: &str) -> Result<Self, InputError> {
+        let invalid = |reason: &str| InputError::InvalidRequireAlias {
+            alias: s.to_string(),
+            reason: reason.to_string(),
+        };
+        if s.is_empty() {
+            return Err(invalid("must not be empty"));
+        }
+        let (callee, argument) = match s.split_once(':') {
+            Some((callee, index)) => match index.parse() {
+                Ok(index) => (callee, index),
+                Err(_) => return Err(invalid("argument index must be a non-negative integer")),
+            },
+            None => (s, 0),
+        };
+        let parts: Vec<&str> = callee.split('.').collect();
         match parts.as_slice() {
-            [method] => RequireAlias {
+            [""] => Err(invalid("must not be empty")),
+            [method] => Ok(RequireAlias {
                 object: None,
                 method: (*method).to_string(),
-            },
+                argument,
+            }),
             [obj, method] => {
-                assert!(
-                    !obj.is_empty() && !method.is_empty(),
-                    "Require alias '{s}' has empty segments",
-                );
-                RequireAlias {
+                if obj.is_empty() || method.is_empty() {
+                    return Err(invalid("has empty segments"));
+                }
+                Ok(RequireAlias {
                     object: Some((*obj).to_string()),
                     method: (*method).to_string(),
-                }
+                    argument,
+                })
             }
-            _ => panic!("Require alias '{s}' must be 'name' or 'object.method'"),
+            _ => Err(invalid("must be 'name' or 'object.method'")),
+        }
+    }
+
+    /// The called function, like `jest.requireActual`.
+    pub fn callee(&self) -> String {
+        match &self.object {
+            Some(object) => format!("{object}.{}", self.method),
+            None => self.method.clone(),
         }
     }
 }
 
+/// The options that change which imports are collected.
+#[derive(Default, Clone, Copy)]
+pub struct CollectOptions<'a> {
+    pub ignore_type_imports: bool,
+    pub require_aliases: &'a [RequireAlias],
+    pub import_rules: &'a [ImportRule],
+    pub track_symbols: bool,
+    pub expand_dynamic_imports: bool,
+}
+
 pub fn collect_imports(
     source_type: SourceType,
     source_text: &str,
     source_filename: Option<&PathBuf>,
-    ignore_type_imports: bool,
-    require_aliases: &[RequireAlias],
+    options: &CollectOptions,
 ) -> ImportsReturn {
+    let CollectOptions {
+        ignore_type_imports,
+        require_aliases,
+        import_rules,
+        track_symbols,
+        expand_dynamic_imports,
+    } = *options;
     let allocator = Allocator::default();
-    let parsed = Parser::new(&allocator, &source_text, source_type).parse();
+    let parsed = Parser::new(&allocator, source_text, source_type).parse();
 
     let program = parsed.program;
 
+    let file = match source_filename {
+        None => "unknown file".to_string(),
+        Some(source_filename) => {
+            let current_dir = env::current_dir().unwrap_or_default();
+            source_filename
+                .strip_prefix(&current_dir)
+                .unwrap_or(source_filename)
+                .to_string_lossy()
+                .into_owned()
+        }
+    };
+
     let mut ast_pass = CollectImports {
-        errors: Vec::new(),
+        file: &file,
+        errors: parsed
+            .errors
+            .iter()
+            .map(|d| parse_error(&file, d))
+            .collect(),
         import_paths: HashSet::new(),
+        contexts: Vec::new(),
+        globs: Vec::new(),
+        symbols: track_symbols.then(ModuleSymbols::default),
         ignore_type_imports,
         require_aliases,
+        import_rules,
+        expand_dynamic_imports,
     };
     ast_pass.visit_program(&program);
+    let mut type_references = Vec::new();
+    if !ignore_type_imports {
+        // Triple-slash directives only count before the first statement.
+        let header_end = program
+            .directives
+            .first()
+            .map(|directive| directive.span.start)
+            .or_else(|| program.body.first().map(|statement| statement.span().start))
+            .unwrap_or(u32::MAX);
+        for comment in program.comments.iter() {
+            let content = comment.content_span().source_text(source_text);
+            if comment.is_line() {
+                if comment.span.end > header_end {
+                    continue;
+                }
+                match reference_directive(content) {
+                    Some(Reference::Path(specifier)) => ast_pass.add_import(&specifier),
+                    Some(Reference::Types(name)) => {
+                        if !type_references.contains(&name) {
+                            type_references.push(name);
+                        }
+                    }
+                    None => {}
+                }
+            } else if content.starts_with('*') && !source_type.is_typescript() {
+                for (specifier, name) in jsdoc_imports(content) {
+                    ast_pass.add_import_with(&specifier, |usage| match name {
+                        Some(name) => {
+                            usage.names.insert(name);
+                        }
+                        None => usage.all = true,
+                    });
+                }
+            }
+        }
+    }
 
-    let errors = if parsed.errors.is_empty() && ast_pass.errors.is_empty() {
-        vec![]
-    } else {
-        let file_name = if source_filename.is_none() {
-            "unknown file"
-        } else {
-            source_filename
-                .unwrap()
-                .strip_prefix(env::current_dir().unwrap())
-                .unwrap()
-                .to_str()
-                .unwrap_or_default()
-        };
-        let source = Arc::new(NamedSource::new(file_name, source_text.to_string()));
-        [parsed.errors, ast_pass.errors]
-            .concat()
-            .into_iter()
-            .map(|diagnostic| Error::from(diagnostic).with_source_code(Arc::clone(&source)))
-            .map(|error| format!("{error:?}"))
-            .collect()
-    };
-
-    let ret = ImportsReturn {
-        errors,
+    ImportsReturn {
+        errors: ast_pass.errors,
         imports_paths: ast_pass.import_paths.into_iter().collect(),
+        contexts: ast_pass.contexts,
+        globs: ast_pass.globs,
+        type_references,
+        symbols: ast_pass.symbols,
+    }
+}
+
+/// A triple-slash reference directive.
+enum Reference {
+    /// A file, relative to the referencing file.
+    Path(String),
+    /// A package name.
+    Types(String),
+}
+
+/// Parses a `/// <reference path="..." />` or `/// <reference types="..." />`
+/// directive from the content of a line comment.
+fn reference_directive(content: &str) -> Option<Reference> {
+    let directive = content
+        .strip_prefix('/')?
+        .trim()
+        .strip_prefix("<reference")?;
+    let directive = directive.trim_end().strip_suffix('>')?;
+    let directive = directive.strip_suffix('/').unwrap_or(directive);
+    let attributes = parse_attributes(directive);
+    let attribute = |name: &str| {
+        attributes
+            .iter()
+            .find(|(key, value)| *key == name && !value.is_empty())
+            .map(|(_, value)| *value)
     };
-    ret
+    if let Some(path) = attribute("path") {
+        if path.starts_with("./") || path.starts_with("../") || path.starts_with('/') {
+            return Some(Reference::Path(path.to_string()));
+        }
+        return Some(Reference::Path(format!("./{path}")));
+    }
+    attribute("types").map(|name| Reference::Types(name.to_string()))
+}
+
+/// Finds the modules referenced by a JSDoc comment, through import types
+/// like `{import('./config').Config}` and TypeScript's `@import` tags like
+/// `@import { Config } from './config'`. The imported name is returned when
+/// an import type picks a single export.
+fn jsdoc_imports(content: &str) -> Vec<(String, Option<String>)> {
+    fn quoted(text: &str) -> Option<(&str, &str)> {
+        let text = text.trim_start();
+        let quote = text
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\'' | '`'))?;
+        let end = text[1..].find(quote)? + 1;
+        Some((&text[1..end], &text[end + 1..]))
+    }
+    fn is_identifier(c: char) -> bool {
+        c.is_alphanumeric() || c == '_' || c == '$'
+    }
+    fn identifier(text: &str) -> &str {
+        let end = text.find(|c: char| !is_identifier(c)).unwrap_or(text.len());
+        &text[..end]
+    }
+    /// The text after the `from` keyword, which is a whole word outside of
+    /// the braces listing the imported names.
+    fn after_from(text: &str) -> Option<&str> {
+        let mut depth = 0usize;
+        for (i, c) in text.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth = depth.saturating_sub(1),
+                'f' if depth == 0 => match text[i..].strip_prefix("from") {
+                    Some(rest)
+                        if !text[..i].ends_with(is_identifier)
+                            && !rest.starts_with(is_identifier) =>
+                    {
+                        return Some(rest);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        None
+    }
+
+    let mut imports = Vec::new();
+    let mut offset = 0;
+    while let Some(found) = content[offset..].find("import") {
+        let start = offset + found;
+        offset = start + "import".len();
+        let after = &content[offset..];
+        match content[..start].chars().next_back() {
+            // An `@import` tag runs until its `from` clause.
+            Some('@') if after.starts_with(char::is_whitespace) => {
+                let Some(from) = after_from(after) else {
+                    continue;
+                };
+                if let Some((specifier, _)) = quoted(from) {
+                    imports.push((specifier.to_string(), None));
+                }
+            }
+            Some(c) if c.is_alphanumeric() || c == '_' || c == '$' || c == '@' => {}
+            _ => {
+                let Some(argument) = after.trim_start().strip_prefix('(') else {
+                    continue;
+                };
+                let Some((specifier, remaining)) = quoted(argument) else {
+                    continue;
+                };
+                let Some(remaining) = remaining.trim_start().strip_prefix(')') else {
+                    continue;
+                };
+                let name = remaining
+                    .strip_prefix('.')
+                    .map(identifier)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string);
+                imports.push((specifier.to_string(), name));
+            }
+        }
+    }
+    imports
+}
+
+fn error_span(span: Span) -> ErrorSpan {
+    ErrorSpan {
+        start: span.start,
+        end: span.end,
+    }
+}
+
+fn parse_error(file: &str, diagnostic: &OxcDiagnostic) -> AffectedError {
+    let span = diagnostic
+        .labels
+        .as_ref()
+        .and_then(|labels| labels.first())
+        .map(|label| ErrorSpan {
+            start: label.offset() as u32,
+            end: (label.offset() + label.len()) as u32,
+        });
+    AffectedError::Parse {
+        file: file.to_string(),
+        message: diagnostic.message.to_string(),
+        span,
+    }
 }
 
 fn is_type_only_import(it: &oxc_ast::ast::ImportDeclaration<'_>) -> bool {
@@ -119,10 +422,16 @@ fn is_type_only_named_export(it: &oxc_ast::ast::ExportNamedDeclaration<'_>) -> b
 }
 
 struct CollectImports<'b> {
-    errors: Vec<OxcDiagnostic>,
+    file: &'b str,
+    errors: Vec<AffectedError>,
     import_paths: HashSet<String>,
+    contexts: Vec<RequireContext>,
+    globs: Vec<ImportGlob>,
+    symbols: Option<ModuleSymbols>,
     ignore_type_imports: bool,
     require_aliases: &'b [RequireAlias],
+    import_rules: &'b [ImportRule],
+    expand_dynamic_imports: bool,
 }
 
 enum RequireCallMatch<'a> {
@@ -131,7 +440,148 @@ enum RequireCallMatch<'a> {
     /// Require-like call with a valid string-literal argument.
     Path(&'a oxc_ast::ast::StringLiteral<'a>),
     /// Require-like call but the argument shape is wrong.
-    InvalidArgs,
+    InvalidArgs { call: String, expected: String },
+}
+
+/// Parses the arguments of a `require.context(directory, recursive, regExp)`
+/// call. Returns `None` if it isn't one, and `Some(None)` if the arguments
+/// aren't literals.
+fn match_require_context(
+    call: &oxc_ast::ast::CallExpression<'_>,
+) -> Option<Option<RequireContext>> {
+    let member = call.callee.as_member_expression()?;
+    let oxc_ast::ast::Expression::Identifier(object) = member.object() else {
+        return None;
+    };
+    if object.name != "require" || member.static_property_name() != Some("context") {
+        return None;
+    }
+    let mut arguments = call.arguments.iter();
+    let directory = match arguments.next() {
+        Some(oxc_ast::ast::Argument::StringLiteral(lit)) => lit.value.to_string(),
+        _ => return Some(None),
+    };
+    let recursive = match arguments.next() {
+        None => true,
+        Some(oxc_ast::ast::Argument::BooleanLiteral(lit)) => lit.value,
+        _ => return Some(None),
+    };
+    let filter = match arguments.next() {
+        None => None,
+        Some(oxc_ast::ast::Argument::RegExpLiteral(lit)) => Some((
+            lit.regex.pattern.text.to_string(),
+            lit.regex.flags.to_string(),
+        )),
+        _ => return Some(None),
+    };
+    // A fourth argument selects the webpack loading mode, which doesn't
+    // change which files are included.
+    Some(Some(RequireContext {
+        directory,
+        recursive,
+        filter,
+    }))
+}
+
+/// Parses the arguments of an `import.meta.glob(patterns, options)` call,
+/// including the deprecated `globEager` variants. Returns `None` if it isn't
+/// one, and `Some(None)` if the arguments aren't supported.
+fn match_import_glob(call: &oxc_ast::ast::CallExpression<'_>) -> Option<Option<ImportGlob>> {
+    let member = call.callee.as_member_expression()?;
+    let oxc_ast::ast::Expression::MetaProperty(meta) = member.object() else {
+        return None;
+    };
+    if meta.meta.name != "import"
+        || meta.property.name != "meta"
+        || !matches!(
+            member.static_property_name(),
+            Some("glob" | "globEager" | "globEagerDefault")
+        )
+    {
+        return None;
+    }
+    let mut arguments = call.arguments.iter();
+    let patterns = match arguments.next() {
+        Some(oxc_ast::ast::Argument::StringLiteral(lit)) => vec![lit.value.to_string()],
+        Some(oxc_ast::ast::Argument::ArrayExpression(array)) => {
+            let mut patterns = Vec::with_capacity(array.elements.len());
+            for element in array.elements.iter() {
+                let oxc_ast::ast::ArrayExpressionElement::StringLiteral(lit) = element else {
+                    return Some(None);
+                };
+                patterns.push(lit.value.to_string());
+            }
+            patterns
+        }
+        _ => return Some(None),
+    };
+    if !patterns.iter().all(|pattern| is_supported_glob(pattern)) {
+        return Some(None);
+    }
+    // Only `exhaustive` changes which files match; `eager`, `import` and
+    // `query` change how they're loaded.
+    let exhaustive = match arguments.next() {
+        None => false,
+        Some(oxc_ast::ast::Argument::ObjectExpression(options)) => {
+            options.properties.iter().any(|property| {
+                matches!(
+                    property,
+                    oxc_ast::ast::ObjectPropertyKind::ObjectProperty(property)
+                        if property.key.static_name().as_deref() == Some("exhaustive")
+                            && matches!(
+                                property.value,
+                                oxc_ast::ast::Expression::BooleanLiteral(ref lit) if lit.value
+                            )
+                )
+            })
+        }
+        _ => return Some(None),
+    };
+    Some(Some(ImportGlob {
+        patterns,
+        exhaustive,
+    }))
+}
+
+/// Turns a template literal or string concatenation with a static relative
+/// prefix, like `./locales/${lang}.json`, into a glob matching every file it
+/// could refer to. Each expression matches within a single path segment.
+fn dynamic_import_glob(expression: &oxc_ast::ast::Expression<'_>) -> Option<ImportGlob> {
+    fn push_wildcard(glob: &mut String) {
+        if !glob.ends_with('*') || glob.ends_with("\\*") {
+            glob.push('*');
+        }
+    }
+    fn push_parts(expression: &oxc_ast::ast::Expression<'_>, glob: &mut String) {
+        match expression {
+            oxc_ast::ast::Expression::StringLiteral(lit) => glob.push_str(&escape_glob(&lit.value)),
+            oxc_ast::ast::Expression::TemplateLiteral(lit) => {
+                for (i, quasi) in lit.quasis.iter().enumerate() {
+                    if i > 0 {
+                        push_wildcard(glob);
+                    }
+                    let value = quasi.value.cooked.unwrap_or(quasi.value.raw);
+                    glob.push_str(&escape_glob(&value));
+                }
+            }
+            oxc_ast::ast::Expression::BinaryExpression(binary)
+                if binary.operator == oxc_ast::ast::BinaryOperator::Addition =>
+            {
+                push_parts(&binary.left, glob);
+                push_parts(&binary.right, glob);
+            }
+            oxc_ast::ast::Expression::ParenthesizedExpression(paren) => {
+                push_parts(&paren.expression, glob);
+            }
+            _ => push_wildcard(glob),
+        }
+    }
+    let mut glob = String::new();
+    push_parts(expression, &mut glob);
+    (glob.starts_with("./") || glob.starts_with("../")).then(|| ImportGlob {
+        patterns: vec![glob],
+        exhaustive: false,
+    })
 }
 
 /// Identifies calls that should be collected as imports: the built-in
@@ -141,12 +591,19 @@ fn match_require_call<'a>(
     call: &'a oxc_ast::ast::CallExpression<'a>,
     aliases: &[RequireAlias],
 ) -> RequireCallMatch<'a> {
-    let matched = match &call.callee {
+    let alias = match &call.callee {
         oxc_ast::ast::Expression::Identifier(id) => {
-            id.name == "require"
-                || aliases
-                    .iter()
-                    .any(|a| a.object.is_none() && a.method == id.name.as_str())
+            if id.name == "require" {
+                return match_require_arguments(
+                    call,
+                    1,
+                    "require",
+                    "a single string literal argument",
+                );
+            }
+            aliases
+                .iter()
+                .find(|a| a.object.is_none() && a.method == id.name.as_str())
         }
         expr => match expr.as_member_expression() {
             Some(member) => {
@@ -156,22 +613,143 @@ fn match_require_call<'a>(
                 let Some(prop_name) = member.static_property_name() else {
                     return RequireCallMatch::None;
                 };
-                aliases.iter().any(|a| {
+                // `require.resolve(specifier, options)` resolves like
+                // `require()` without loading the module.
+                if obj_id.name == "require" && prop_name == "resolve" {
+                    return match_require_arguments(
+                        call,
+                        2,
+                        "require.resolve",
+                        "a string literal argument, optionally followed by options",
+                    );
+                }
+                aliases.iter().find(|a| {
                     a.object.as_deref() == Some(obj_id.name.as_str()) && a.method == prop_name
                 })
             }
-            None => false,
+            None => None,
         },
     };
-    if !matched {
+    let Some(alias) = alias else {
         return RequireCallMatch::None;
+    };
+    match call.arguments.get(alias.argument) {
+        Some(oxc_ast::ast::Argument::StringLiteral(lit)) => RequireCallMatch::Path(lit),
+        _ => RequireCallMatch::InvalidArgs {
+            call: alias.callee(),
+            expected: format!("a string literal argument at index {}", alias.argument),
+        },
     }
-    if call.arguments.len() != 1 {
-        return RequireCallMatch::InvalidArgs;
+}
+
+fn match_require_arguments<'a>(
+    call: &'a oxc_ast::ast::CallExpression<'a>,
+    max_arguments: usize,
+    name: &str,
+    expected: &str,
+) -> RequireCallMatch<'a> {
+    let invalid = || RequireCallMatch::InvalidArgs {
+        call: name.to_string(),
+        expected: expected.to_string(),
+    };
+    if call.arguments.len() > max_arguments {
+        return invalid();
     }
     match call.arguments.first() {
         Some(oxc_ast::ast::Argument::StringLiteral(lit)) => RequireCallMatch::Path(lit),
-        _ => RequireCallMatch::InvalidArgs,
+        _ => invalid(),
+    }
+}
+
+fn is_import_meta(expression: &oxc_ast::ast::Expression<'_>) -> bool {
+    matches!(
+        expression,
+        oxc_ast::ast::Expression::MetaProperty(meta)
+            if meta.meta.name == "import" && meta.property.name == "meta"
+    )
+}
+
+/// Matches `import.meta.resolve(specifier)`. Returns the specifier, or
+/// `Some(None)` if it isn't a string literal.
+fn match_import_meta_resolve<'a>(
+    call: &'a oxc_ast::ast::CallExpression<'a>,
+) -> Option<Option<&'a oxc_ast::ast::StringLiteral<'a>>> {
+    let member = call.callee.as_member_expression()?;
+    if !is_import_meta(member.object()) || member.static_property_name() != Some("resolve") {
+        return None;
+    }
+    match call.arguments.first() {
+        Some(oxc_ast::ast::Argument::StringLiteral(lit)) => Some(Some(lit)),
+        _ => Some(None),
+    }
+}
+
+/// Matches `new URL(path, import.meta.url)`, which bundlers treat as a
+/// reference to an asset relative to the current module. Returns the path
+/// as a relative specifier; URLs with a scheme and root-relative paths
+/// aren't files in the project.
+fn match_asset_url(new: &oxc_ast::ast::NewExpression<'_>) -> Option<String> {
+    let oxc_ast::ast::Expression::Identifier(callee) = &new.callee else {
+        return None;
+    };
+    let [oxc_ast::ast::Argument::StringLiteral(path), base] = new.arguments.as_slice() else {
+        return None;
+    };
+    let base = base.as_expression()?.as_member_expression()?;
+    if callee.name != "URL"
+        || !is_import_meta(base.object())
+        || base.static_property_name() != Some("url")
+    {
+        return None;
+    }
+    let path = path.value.as_str();
+    let has_scheme = path
+        .split_once(':')
+        .is_some_and(|(scheme, _)| !scheme.is_empty() && !scheme.contains('/'));
+    if has_scheme || path.is_empty() || path.starts_with('/') {
+        return None;
+    }
+    if path.starts_with("./") || path.starts_with("../") {
+        Some(path.to_string())
+    } else {
+        Some(format!("./{path}"))
+    }
+}
+
+impl CollectImports<'_> {
+    /// Records an import of the whole module.
+    fn add_import(&mut self, specifier: &str) {
+        self.add_import_with(specifier, |usage| usage.all = true);
+    }
+
+    fn add_import_with(&mut self, specifier: &str, update: impl FnOnce(&mut ImportUsage)) {
+        self.import_paths.insert(specifier.to_string());
+        if let Some(symbols) = &mut self.symbols {
+            update(symbols.usages.entry(specifier.to_string()).or_default());
+        }
+    }
+
+    fn add_export(&mut self, name: &str) {
+        if let Some(symbols) = &mut self.symbols {
+            symbols.exports.insert(name.to_string());
+        }
+    }
+
+    fn unsupported_dynamic_import(&mut self, span: Span, call: &str) {
+        self.errors.push(AffectedError::UnsupportedDynamicImport {
+            file: self.file.to_string(),
+            span: error_span(span),
+            call: call.to_string(),
+        });
+    }
+
+    fn invalid_arguments(&mut self, span: Span, call: String, expected: String) {
+        self.errors.push(AffectedError::InvalidRequireArguments {
+            file: self.file.to_string(),
+            span: error_span(span),
+            call,
+            expected,
+        });
     }
 }
 
@@ -180,33 +758,51 @@ impl<'a, 'b> Visit<'a> for CollectImports<'b> {
         if self.ignore_type_imports && is_type_only_import(it) {
             return;
         }
-        self.import_paths.insert(it.source.value.to_string());
+        let ignore_type_imports = self.ignore_type_imports;
+        self.add_import_with(it.source.value.as_str(), |usage| {
+            let specifiers = match &it.specifiers {
+                Some(specifiers) if !specifiers.is_empty() => specifiers,
+                // Imported for its side effects.
+                _ => {
+                    usage.all = true;
+                    return;
+                }
+            };
+            for spec in specifiers.iter() {
+                match spec {
+                    oxc_ast::ast::ImportDeclarationSpecifier::ImportSpecifier(s) => {
+                        if !(ignore_type_imports && s.import_kind.is_type()) {
+                            usage.names.insert(s.imported.name().to_string());
+                        }
+                    }
+                    oxc_ast::ast::ImportDeclarationSpecifier::ImportDefaultSpecifier(_) => {
+                        usage.names.insert("default".to_string());
+                    }
+                    oxc_ast::ast::ImportDeclarationSpecifier::ImportNamespaceSpecifier(_) => {
+                        usage.all = true;
+                    }
+                }
+            }
+        });
         walk::walk_import_declaration(self, it);
     }
 
     fn visit_import_expression(&mut self, it: &oxc_ast::ast::ImportExpression<'a>) {
         match &it.source {
             oxc_ast::ast::Expression::StringLiteral(literal) => {
-                self.import_paths.insert(literal.value.to_string());
+                self.add_import(literal.value.as_str());
             }
-            oxc_ast::ast::Expression::TemplateLiteral(literal) => {
-                if literal.expressions.len() == 0 {
-                    if let Some(first) = literal.quasis.first() {
-                        self.import_paths.insert(first.value.raw.to_string());
-                    }
-                } else {
-                    self.errors.push(
-                        OxcDiagnostic::error("Import call must not have dynamic template literals")
-                            .with_label(it.span),
-                    );
+            oxc_ast::ast::Expression::TemplateLiteral(literal)
+                if literal.expressions.is_empty() =>
+            {
+                if let Some(first) = literal.quasis.first() {
+                    self.add_import(first.value.raw.as_str());
                 }
             }
-            _ => {
-                self.errors.push(
-                    OxcDiagnostic::error("Import call must not have dynamic template literals")
-                        .with_label(it.span),
-                );
-            }
+            source => match dynamic_import_glob(source).filter(|_| self.expand_dynamic_imports) {
+                Some(glob) => self.globs.push(glob),
+                None => self.unsupported_dynamic_import(it.span, "import"),
+            },
         }
         walk::walk_import_expression(self, it);
     }
@@ -215,17 +811,70 @@ impl<'a, 'b> Visit<'a> for CollectImports<'b> {
         if self.ignore_type_imports && is_type_only_named_export(it) {
             return;
         }
-        if let Some(source) = &it.source {
-            self.import_paths.insert(source.value.to_string());
+        let ignore_type_imports = self.ignore_type_imports;
+        let exported = it
+            .specifiers
+            .iter()
+            .filter(|spec| !(ignore_type_imports && spec.export_kind.is_type()));
+        match &it.source {
+            Some(source) => {
+                self.add_import_with(source.value.as_str(), |usage| {
+                    usage.reexports.extend(exported.map(|spec| {
+                        (
+                            spec.local.name().to_string(),
+                            spec.exported.name().to_string(),
+                        )
+                    }));
+                });
+            }
+            None if self.symbols.is_some() => {
+                let mut names: Vec<String> = exported
+                    .map(|spec| spec.exported.name().to_string())
+                    .collect();
+                match &it.declaration {
+                    Some(oxc_ast::ast::Declaration::VariableDeclaration(decl)) => {
+                        for declarator in decl.declarations.iter() {
+                            names.extend(
+                                declarator
+                                    .id
+                                    .get_binding_identifiers()
+                                    .iter()
+                                    .map(|id| id.name.to_string()),
+                            );
+                        }
+                    }
+                    Some(decl) => names.extend(decl.id().map(|id| id.name.to_string())),
+                    None => {}
+                }
+                for name in names {
+                    self.add_export(&name);
+                }
+            }
+            None => {}
         }
         walk::walk_export_named_declaration(self, it);
     }
 
+    fn visit_export_default_declaration(
+        &mut self,
+        it: &oxc_ast::ast::ExportDefaultDeclaration<'a>,
+    ) {
+        self.add_export("default");
+        walk::walk_export_default_declaration(self, it);
+    }
+
     fn visit_export_all_declaration(&mut self, it: &oxc_ast::ast::ExportAllDeclaration<'a>) {
         if self.ignore_type_imports && it.export_kind.is_type() {
             return;
         }
-        self.import_paths.insert(it.source.value.to_string());
+        self.add_import_with(it.source.value.as_str(), |usage| match &it.exported {
+            Some(exported) => {
+                usage
+                    .reexports
+                    .insert(("*".to_string(), exported.name().to_string()));
+            }
+            None => usage.reexports_all = true,
+        });
         walk::walk_export_all_declaration(self, it);
     }
 
@@ -233,7 +882,7 @@ impl<'a, 'b> Visit<'a> for CollectImports<'b> {
         if self.ignore_type_imports {
             return;
         }
-        self.import_paths.insert(it.source.value.to_string());
+        self.add_import(it.source.value.as_str());
         walk::walk_ts_import_type(self, it);
     }
 
@@ -246,22 +895,88 @@ impl<'a, 'b> Visit<'a> for CollectImports<'b> {
         }
         if let oxc_ast::ast::TSModuleReference::ExternalModuleReference(ext) = &it.module_reference
         {
-            self.import_paths.insert(ext.expression.value.to_string());
+            self.add_import(ext.expression.value.as_str());
         }
         walk::walk_ts_import_equals_declaration(self, it);
     }
 
+    fn visit_new_expression(&mut self, it: &oxc_ast::ast::NewExpression<'a>) {
+        if let Some(specifier) = match_asset_url(it) {
+            self.add_import(&specifier);
+        }
+        walk::walk_new_expression(self, it);
+    }
+
     fn visit_call_expression(&mut self, it: &oxc_ast::ast::CallExpression<'a>) {
-        match match_require_call(it, &self.require_aliases) {
+        if let Some(context) = match_require_context(it) {
+            match context {
+                Some(context) => self.contexts.push(context),
+                None => self.invalid_arguments(
+                    it.span,
+                    "require.context".to_string(),
+                    "a string literal directory, optionally followed by a boolean and a \
+                     regular expression literal"
+                        .to_string(),
+                ),
+            }
+            walk::walk_call_expression(self, it);
+            return;
+        }
+        if let Some(glob) = match_import_glob(it) {
+            match glob {
+                Some(glob) => self.globs.push(glob),
+                None => self.errors.push(AffectedError::InvalidGlobArguments {
+                    file: self.file.to_string(),
+                    span: error_span(it.span),
+                }),
+            }
+            walk::walk_call_expression(self, it);
+            return;
+        }
+        if let Some(specifier) = match_import_meta_resolve(it) {
+            match specifier {
+                Some(specifier) => self.add_import(specifier.value.as_str()),
+                None => self.unsupported_dynamic_import(it.span, "import.meta.resolve"),
+            }
+            walk::walk_call_expression(self, it);
+            return;
+        }
+        if let Some(callee) = callee_segments(&it.callee) {
+            let mut matched = false;
+            for rule in self.import_rules.iter() {
+                if !rule.matches_callee(&callee) {
+                    continue;
+                }
+                matched = true;
+                match rule.specifier(it) {
+                    Some(_) if rule.type_only && self.ignore_type_imports => {}
+                    Some(specifier) => self.add_import(&specifier),
+                    None => self.invalid_arguments(it.span, callee.join("."), rule.expected()),
+                }
+            }
+            if matched {
+                walk::walk_call_expression(self, it);
+                return;
+            }
+        }
+        match match_require_call(it, self.require_aliases) {
             RequireCallMatch::None => {}
             RequireCallMatch::Path(literal) => {
-                self.import_paths.insert(literal.value.to_string());
+                self.add_import(literal.value.as_str());
             }
-            RequireCallMatch::InvalidArgs => {
-                self.errors.push(
-                    OxcDiagnostic::error("Require call must have a single string literal argument")
-                        .with_label(it.span),
-                );
+            RequireCallMatch::InvalidArgs { call, expected } => {
+                let glob = match it.arguments.as_slice() {
+                    [argument] if self.expand_dynamic_imports => {
+                        argument.as_expression().and_then(dynamic_import_glob)
+                    }
+                    _ => None,
+                };
+                if let Some(glob) = glob {
+                    self.globs.push(glob);
+                    walk::walk_call_expression(self, it);
+                    return;
+                }
+                self.invalid_arguments(it.span, call, expected);
             }
         }
         walk::walk_call_expression(self, it);
@@ -273,11 +988,16 @@ mod tests {
     use super::*;
 
     fn assert_imports(source_text: &str, expected_imports: Vec<&str>) {
-        let ret = collect_imports(SourceType::mjs(), source_text, None, false, &[]);
+        let ret = collect_imports(
+            SourceType::mjs(),
+            source_text,
+            None,
+            &CollectOptions::default(),
+        );
         // Convert to HashSet to ignore order
         let expected: HashSet<String> =
             HashSet::from_iter(expected_imports.into_iter().map(|s| s.to_string()));
-        let actual: HashSet<String> = HashSet::from_iter(ret.imports_paths.into_iter());
+        let actual: HashSet<String> = HashSet::from_iter(ret.imports_paths);
         assert_eq!(expected, actual);
         assert!(ret.errors.is_empty());
     }
@@ -287,11 +1007,22 @@ mod tests {
         aliases: Vec<&str>,
         expected_imports: Vec<&str>,
     ) {
-        let parsed: Vec<RequireAlias> = aliases.iter().map(|s| RequireAlias::parse(s)).collect();
-        let ret = collect_imports(SourceType::mjs(), source_text, None, false, &parsed);
+        let parsed: Vec<RequireAlias> = aliases
+            .iter()
+            .map(|s| RequireAlias::parse(s).unwrap())
+            .collect();
+        let ret = collect_imports(
+            SourceType::mjs(),
+            source_text,
+            None,
+            &CollectOptions {
+                require_aliases: &parsed,
+                ..CollectOptions::default()
+            },
+        );
         let expected: HashSet<String> =
             HashSet::from_iter(expected_imports.into_iter().map(|s| s.to_string()));
-        let actual: HashSet<String> = HashSet::from_iter(ret.imports_paths.into_iter());
+        let actual: HashSet<String> = HashSet::from_iter(ret.imports_paths);
         assert_eq!(expected, actual);
         assert!(ret.errors.is_empty());
     }
@@ -305,18 +1036,25 @@ mod tests {
             SourceType::ts(),
             source_text,
             None,
-            ignore_type_imports,
-            &[],
+            &CollectOptions {
+                ignore_type_imports,
+                ..CollectOptions::default()
+            },
         );
         let expected: HashSet<String> =
             HashSet::from_iter(expected_imports.into_iter().map(|s| s.to_string()));
-        let actual: HashSet<String> = HashSet::from_iter(ret.imports_paths.into_iter());
+        let actual: HashSet<String> = HashSet::from_iter(ret.imports_paths);
         assert_eq!(expected, actual);
         assert!(ret.errors.is_empty());
     }
 
     fn asset_error(source_text: &str) {
-        let ret = collect_imports(SourceType::mjs(), source_text, None, false, &[]);
+        let ret = collect_imports(
+            SourceType::mjs(),
+            source_text,
+            None,
+            &CollectOptions::default(),
+        );
         assert!(!ret.errors.is_empty());
         assert!(ret.imports_paths.is_empty());
     }
@@ -379,14 +1117,123 @@ mod tests {
         asset_error("import snel from 'hest'; const;");
     }
 
+    #[test]
+    fn test_invalid_syntax_error_kind() {
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "import 'snel'; const;",
+            None,
+            &CollectOptions::default(),
+        );
+        let [AffectedError::Parse { file, span, .. }] = ret.errors.as_slice() else {
+            panic!("expected a single parse error, got {:?}", ret.errors);
+        };
+        assert_eq!(file, "unknown file");
+        assert!(span.is_some());
+    }
+
+    #[test]
+    fn test_dynamic_import_error_kind() {
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "import(path);",
+            None,
+            &CollectOptions::default(),
+        );
+        assert_eq!(
+            ret.errors,
+            vec![AffectedError::UnsupportedDynamicImport {
+                file: "unknown file".to_string(),
+                span: ErrorSpan { start: 0, end: 12 },
+                call: "import".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_require_error_kind() {
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "require(path);",
+            None,
+            &CollectOptions::default(),
+        );
+        assert_eq!(
+            ret.errors,
+            vec![AffectedError::InvalidRequireArguments {
+                file: "unknown file".to_string(),
+                span: ErrorSpan { start: 0, end: 13 },
+                call: "require".to_string(),
+                expected: "a single string literal argument".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_arguments_messages() {
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "import(name);
+            import.meta.resolve(name);
+            require(name);
+            require.resolve(name);
+            require.context(dir);
+            jest.requireActual(name);
+            loadModule('./a.js', name);
+            loadComponent(name);
+            route({ component: name });",
+            None,
+            &CollectOptions {
+                require_aliases: &[
+                    RequireAlias::parse("jest.requireActual").unwrap(),
+                    RequireAlias::parse("loadModule:1").unwrap(),
+                ],
+                import_rules: &[
+                    ImportRule {
+                        callee: "loadComponent".to_string(),
+                        argument: 0,
+                        ..ImportRule::default()
+                    },
+                    ImportRule {
+                        callee: "route".to_string(),
+                        property: Some("component".to_string()),
+                        ..ImportRule::default()
+                    },
+                ],
+                ..CollectOptions::default()
+            },
+        );
+        let messages: Vec<String> = ret
+            .errors
+            .iter()
+            .map(|e| e.message().into_owned())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "import() must have a string literal argument without dynamic parts",
+                "import.meta.resolve() must have a string literal argument without dynamic parts",
+                "require() must have a single string literal argument",
+                "require.resolve() must have a string literal argument, optionally followed by \
+                 options",
+                "require.context() must have a string literal directory, optionally followed by \
+                 a boolean and a regular expression literal",
+                "jest.requireActual() must have a string literal argument at index 0",
+                "loadModule() must have a string literal argument at index 1",
+                "loadComponent() must have a string literal argument at index 0",
+                "route() must have an object literal with a string literal 'component' property \
+                 at argument index 0",
+            ]
+        );
+    }
+
     #[test]
     fn test_empty_require() {
         let ret = collect_imports(
             SourceType::mjs(),
             "require('snel'); require();",
             None,
-            false,
-            &[],
+            &CollectOptions::default(),
         );
         assert!(!ret.errors.is_empty());
         assert_eq!(ret.imports_paths, vec!["snel"]);
@@ -398,8 +1245,7 @@ mod tests {
             SourceType::mjs(),
             "require('snel'); const path = 'hest'; require(path);",
             None,
-            false,
-            &[],
+            &CollectOptions::default(),
         );
         assert!(!ret.errors.is_empty());
         assert_eq!(ret.imports_paths, vec!["snel"]);
@@ -411,8 +1257,7 @@ mod tests {
             SourceType::mjs(),
             "import 'snel'; const path = 'hest'; import(path);",
             None,
-            false,
-            &[],
+            &CollectOptions::default(),
         );
         assert!(!ret.errors.is_empty());
         assert_eq!(ret.imports_paths, vec!["snel"]);
@@ -424,8 +1269,7 @@ mod tests {
             SourceType::mjs(),
             "import 'snel'; const path = 'hest'; import(`${path}`);",
             None,
-            false,
-            &[],
+            &CollectOptions::default(),
         );
         assert!(!ret.errors.is_empty());
         assert_eq!(ret.imports_paths, vec!["snel"]);
@@ -437,8 +1281,7 @@ mod tests {
             SourceType::mjs(),
             "const path = 'hest'; require(`${path}`);",
             None,
-            false,
-            &[],
+            &CollectOptions::default(),
         );
         assert!(!ret.errors.is_empty());
     }
@@ -449,8 +1292,7 @@ mod tests {
             SourceType::mjs(),
             "import 'snel'; import('he' + 'st');",
             None,
-            false,
-            &[],
+            &CollectOptions::default(),
         );
         assert!(!ret.errors.is_empty());
         assert_eq!(ret.imports_paths, vec!["snel"]);
@@ -578,15 +1420,95 @@ mod tests {
         );
     }
 
+    // ---- symbol tracking -------------------------------------------------
+
+    #[test]
+    fn test_symbols() {
+        let ret = collect_imports(
+            SourceType::ts(),
+            "import D, { a, b as c, type T } from './named';
+            import * as ns from './namespace';
+            import './side-effect';
+            export { x, y as z } from './reexport';
+            export * from './star';
+            export * as all from './star-as';
+            const r = require('./required');
+            export const local = 1, { destructured } = {};
+            export function fn() {}
+            export { r as renamed };
+            export default class {}",
+            None,
+            &CollectOptions {
+                ignore_type_imports: true,
+                track_symbols: true,
+                ..CollectOptions::default()
+            },
+        );
+        let symbols = ret.symbols.unwrap();
+        let usage = |specifier: &str| symbols.usages[specifier].clone();
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+
+        assert_eq!(
+            usage("./named"),
+            ImportUsage {
+                names: names(&["a", "b", "default"]),
+                ..ImportUsage::default()
+            }
+        );
+        assert_eq!(usage("./namespace"), ImportUsage::all());
+        assert_eq!(usage("./side-effect"), ImportUsage::all());
+        assert_eq!(usage("./required"), ImportUsage::all());
+        assert_eq!(
+            usage("./reexport"),
+            ImportUsage {
+                reexports: BTreeSet::from([
+                    ("x".to_string(), "x".to_string()),
+                    ("y".to_string(), "z".to_string()),
+                ]),
+                ..ImportUsage::default()
+            }
+        );
+        assert_eq!(
+            usage("./star"),
+            ImportUsage {
+                reexports_all: true,
+                ..ImportUsage::default()
+            }
+        );
+        assert_eq!(
+            usage("./star-as"),
+            ImportUsage {
+                reexports: BTreeSet::from([("*".to_string(), "all".to_string())]),
+                ..ImportUsage::default()
+            }
+        );
+        assert_eq!(
+            symbols.exports,
+            names(&["default", "destructured", "fn", "local", "renamed"])
+        );
+    }
+
+    #[test]
+    fn test_symbols_not_tracked_by_default() {
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "import { a } from 'a';",
+            None,
+            &CollectOptions::default(),
+        );
+        assert!(ret.symbols.is_none());
+    }
+
     // ---- RequireAlias::parse ---------------------------------------------
 
     #[test]
     fn test_alias_parse_bare() {
         assert_eq!(
-            RequireAlias::parse("requireActual"),
+            RequireAlias::parse("requireActual").unwrap(),
             RequireAlias {
                 object: None,
                 method: "requireActual".to_string(),
+                argument: 0,
             }
         );
     }
@@ -594,36 +1516,55 @@ mod tests {
     #[test]
     fn test_alias_parse_member() {
         assert_eq!(
-            RequireAlias::parse("jest.requireActual"),
+            RequireAlias::parse("jest.requireActual").unwrap(),
             RequireAlias {
                 object: Some("jest".to_string()),
                 method: "requireActual".to_string(),
+                argument: 0,
             }
         );
     }
 
     #[test]
-    #[should_panic(expected = "must not be empty")]
-    fn test_alias_parse_empty_panics() {
-        RequireAlias::parse("");
+    fn test_alias_parse_argument_index() {
+        assert_eq!(
+            RequireAlias::parse("loader.load:1").unwrap(),
+            RequireAlias {
+                object: Some("loader".to_string()),
+                method: "load".to_string(),
+                argument: 1,
+            }
+        );
+        let err = RequireAlias::parse("loader.load:x").unwrap_err();
+        assert!(err.to_string().contains("non-negative integer"));
+        let err = RequireAlias::parse(":1").unwrap_err();
+        assert!(err.to_string().contains("must not be empty"));
+    }
+
+    #[test]
+    fn test_alias_parse_empty_errors() {
+        let err = RequireAlias::parse("").unwrap_err();
+        assert!(err.to_string().contains("must not be empty"));
     }
 
     #[test]
-    #[should_panic(expected = "empty segments")]
-    fn test_alias_parse_leading_dot_panics() {
-        RequireAlias::parse(".foo");
+    fn test_alias_parse_leading_dot_errors() {
+        let err = RequireAlias::parse(".foo").unwrap_err();
+        assert!(err.to_string().contains("empty segments"));
     }
 
     #[test]
-    #[should_panic(expected = "empty segments")]
-    fn test_alias_parse_trailing_dot_panics() {
-        RequireAlias::parse("foo.");
+    fn test_alias_parse_trailing_dot_errors() {
+        let err = RequireAlias::parse("foo.").unwrap_err();
+        assert!(err.to_string().contains("empty segments"));
     }
 
     #[test]
-    #[should_panic(expected = "must be 'name' or 'object.method'")]
-    fn test_alias_parse_too_many_segments_panics() {
-        RequireAlias::parse("foo.bar.baz");
+    fn test_alias_parse_too_many_segments_errors() {
+        let err = RequireAlias::parse("foo.bar.baz").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("must be 'name' or 'object.method'"));
     }
 
     // ---- visitor: require-alias matching ----------------------------------
@@ -700,15 +1641,119 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_alias_extra_arguments_ignored() {
+        assert_imports_with_aliases(
+            "jest.mock('./api', () => ({ fetch: jest.fn() }));
+            vi.doMock('./db', async () => ({}));
+            jest.setMock('./config', { debug: true });
+            jest.createMockFromModule('./user');",
+            vec![
+                "jest.mock",
+                "vi.doMock",
+                "jest.setMock",
+                "jest.createMockFromModule",
+            ],
+            vec!["./api", "./db", "./config", "./user"],
+        );
+    }
+
+    #[test]
+    fn test_alias_argument_index() {
+        assert_imports_with_aliases(
+            "load(bundle, './a', { lazy: true }); loader.load(bundle, './b');",
+            vec!["load:1", "loader.load:1"],
+            vec!["./a", "./b"],
+        );
+    }
+
+    #[test]
+    fn test_alias_missing_argument_index_errors() {
+        let parsed = vec![RequireAlias::parse("load:1").unwrap()];
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "load('./a');",
+            None,
+            &CollectOptions {
+                require_aliases: &parsed,
+                ..CollectOptions::default()
+            },
+        );
+        assert_eq!(ret.errors.len(), 1);
+        assert!(ret.imports_paths.is_empty());
+    }
+
+    // ---- import rules ----------------------------------------------------
+
+    #[test]
+    fn test_import_rules() {
+        let rules = vec![
+            ImportRule {
+                callee: "loadComponent".to_string(),
+                prefix: Some("@/widgets/".to_string()),
+                ..ImportRule::default()
+            },
+            ImportRule {
+                callee: "app.i18n.load".to_string(),
+                ..ImportRule::default()
+            },
+            ImportRule {
+                callee: "defineSchema".to_string(),
+                property: Some("types".to_string()),
+                type_only: true,
+                ..ImportRule::default()
+            },
+        ];
+        let source_text = "loadComponent('widgets/Chart');
+            app.i18n.load('./fr.json');
+            defineSchema({ types: './schema' });
+            lazyRoute(() => import('./routes/home'));
+            loadComponent(name);";
+        let collect = |ignore_type_imports| {
+            collect_imports(
+                SourceType::mjs(),
+                source_text,
+                None,
+                &CollectOptions {
+                    ignore_type_imports,
+                    import_rules: &rules,
+                    ..CollectOptions::default()
+                },
+            )
+        };
+        let ret = collect(false);
+        let actual: HashSet<String> = HashSet::from_iter(ret.imports_paths);
+        let expected: HashSet<String> = HashSet::from_iter(
+            [
+                "@/widgets/widgets/Chart",
+                "./fr.json",
+                "./schema",
+                "./routes/home",
+            ]
+            .map(str::to_string),
+        );
+        assert_eq!(actual, expected);
+        assert_eq!(ret.errors.len(), 1);
+        assert!(matches!(
+            ret.errors[0],
+            AffectedError::InvalidRequireArguments { .. }
+        ));
+
+        let ret = collect(true);
+        assert!(!ret.imports_paths.iter().any(|p| p == "./schema"));
+    }
+
     #[test]
     fn test_alias_no_arguments_errors() {
-        let parsed = vec![RequireAlias::parse("jest.requireActual")];
+        let parsed = vec![RequireAlias::parse("jest.requireActual").unwrap()];
         let ret = collect_imports(
             SourceType::mjs(),
             "jest.requireActual();",
             None,
-            false,
-            &parsed,
+            &CollectOptions {
+                require_aliases: &parsed,
+                ..CollectOptions::default()
+            },
         );
         assert!(!ret.errors.is_empty());
         assert!(ret.imports_paths.is_empty());
@@ -716,13 +1761,15 @@ mod tests {
 
     #[test]
     fn test_alias_variable_argument_errors() {
-        let parsed = vec![RequireAlias::parse("jest.requireActual")];
+        let parsed = vec![RequireAlias::parse("jest.requireActual").unwrap()];
         let ret = collect_imports(
             SourceType::mjs(),
             "const x = 'hest'; jest.requireActual(x);",
             None,
-            false,
-            &parsed,
+            &CollectOptions {
+                require_aliases: &parsed,
+                ..CollectOptions::default()
+            },
         );
         assert!(!ret.errors.is_empty());
         assert!(ret.imports_paths.is_empty());
@@ -731,15 +1778,335 @@ mod tests {
     #[test]
     fn test_alias_template_literal_argument_errors() {
         // Template literals are not accepted (mirrors require() handling).
-        let parsed = vec![RequireAlias::parse("jest.requireActual")];
+        let parsed = vec![RequireAlias::parse("jest.requireActual").unwrap()];
         let ret = collect_imports(
             SourceType::mjs(),
             "jest.requireActual(`hest`);",
             None,
-            false,
-            &parsed,
+            &CollectOptions {
+                require_aliases: &parsed,
+                ..CollectOptions::default()
+            },
         );
         assert!(!ret.errors.is_empty());
         assert!(ret.imports_paths.is_empty());
     }
+
+    #[test]
+    fn test_require_context() {
+        let ret = collect_imports(
+            SourceType::mjs(),
+            r"require.context('./stories', false, /\.stories\.js$/i); require.context('../lib');",
+            None,
+            &CollectOptions::default(),
+        );
+        assert!(ret.errors.is_empty());
+        assert!(ret.imports_paths.is_empty());
+        assert_eq!(
+            ret.contexts,
+            vec![
+                RequireContext {
+                    directory: "./stories".to_string(),
+                    recursive: false,
+                    filter: Some((r"\.stories\.js$".to_string(), "i".to_string())),
+                },
+                RequireContext {
+                    directory: "../lib".to_string(),
+                    recursive: true,
+                    filter: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_require_context_variable_argument_errors() {
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "require.context(dir, true);",
+            None,
+            &CollectOptions::default(),
+        );
+        assert!(matches!(
+            ret.errors.as_slice(),
+            [AffectedError::InvalidRequireArguments { .. }]
+        ));
+        assert!(ret.contexts.is_empty());
+    }
+
+    #[test]
+    fn test_import_meta_glob() {
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "import.meta.glob('./pages/*.tsx');
+            import.meta.glob(['./a/*.js', '!./a/b.js'], { eager: true, exhaustive: true });
+            import.meta.globEager('/locales/*.json');",
+            None,
+            &CollectOptions::default(),
+        );
+        assert!(ret.errors.is_empty());
+        let globs: Vec<(Vec<String>, bool)> = ret
+            .globs
+            .into_iter()
+            .map(|glob| (glob.patterns, glob.exhaustive))
+            .collect();
+        assert_eq!(
+            globs,
+            vec![
+                (vec!["./pages/*.tsx".to_string()], false),
+                (vec!["./a/*.js".to_string(), "!./a/b.js".to_string()], true),
+                (vec!["/locales/*.json".to_string()], false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_meta_glob_invalid_arguments() {
+        for source_text in [
+            "import.meta.glob(pattern);",
+            "import.meta.glob('@/pages/*.tsx');",
+            "import.meta.glob(['./a/*.js', dir]);",
+        ] {
+            let ret = collect_imports(
+                SourceType::mjs(),
+                source_text,
+                None,
+                &CollectOptions::default(),
+            );
+            assert!(
+                matches!(
+                    ret.errors.as_slice(),
+                    [AffectedError::InvalidGlobArguments { .. }]
+                ),
+                "{source_text}"
+            );
+            assert!(ret.globs.is_empty());
+        }
+    }
+
+    fn expanded_globs(source_text: &str) -> Vec<String> {
+        let ret = collect_imports(
+            SourceType::mjs(),
+            source_text,
+            None,
+            &CollectOptions {
+                expand_dynamic_imports: true,
+                ..CollectOptions::default()
+            },
+        );
+        assert!(ret.errors.is_empty(), "{:?}", ret.errors);
+        ret.globs
+            .into_iter()
+            .flat_map(|glob| glob.patterns)
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_dynamic_imports() {
+        assert_eq!(
+            expanded_globs("import(`./locales/${lang}.json`);"),
+            vec!["./locales/*.json"]
+        );
+        assert_eq!(
+            expanded_globs("import(`../plugins/${a}${b}/index.js`);"),
+            vec!["../plugins/*/index.js"]
+        );
+        assert_eq!(
+            expanded_globs("require('./x/' + name + '.js');"),
+            vec!["./x/*.js"]
+        );
+        assert_eq!(
+            expanded_globs("require(`./[id]/${name}`);"),
+            vec![r"./\[id\]/*"]
+        );
+    }
+
+    #[test]
+    fn test_expand_dynamic_imports_errors() {
+        for source_text in ["import(`${dir}/a.js`);", "import(name + '.js');"] {
+            let ret = collect_imports(
+                SourceType::mjs(),
+                source_text,
+                None,
+                &CollectOptions {
+                    expand_dynamic_imports: true,
+                    ..CollectOptions::default()
+                },
+            );
+            assert!(
+                matches!(
+                    ret.errors.as_slice(),
+                    [AffectedError::UnsupportedDynamicImport { .. }]
+                ),
+                "{source_text}"
+            );
+            assert!(ret.globs.is_empty());
+        }
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "require('./x/' + name);",
+            None,
+            &CollectOptions {
+                expand_dynamic_imports: true,
+                ..CollectOptions::default()
+            },
+        );
+        assert!(ret.errors.is_empty());
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "require('./x/' + name);",
+            None,
+            &CollectOptions::default(),
+        );
+        assert!(matches!(
+            ret.errors.as_slice(),
+            [AffectedError::InvalidRequireArguments { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_asset_urls() {
+        assert_imports(
+            "new Worker(new URL('./worker.ts', import.meta.url));
+            new URL('../wasm/helper.wasm', import.meta.url);
+            new URL('logo.svg', import.meta.url);",
+            vec!["./worker.ts", "../wasm/helper.wasm", "./logo.svg"],
+        );
+    }
+
+    #[test]
+    fn test_asset_urls_not_in_project() {
+        assert_imports(
+            "new URL('https://example.com/a.js', import.meta.url);
+            new URL('data:text/javascript,', import.meta.url);
+            new URL('/public/logo.svg', import.meta.url);
+            new URL('./a.js', location.href);
+            new URL('./a.js');
+            new URL(path, import.meta.url);",
+            vec![],
+        );
+    }
+
+    #[test]
+    fn test_resolve_calls() {
+        assert_imports(
+            "import.meta.resolve('./a.js');
+            require.resolve('b');
+            require.resolve('./c.js', { paths: [__dirname] });",
+            vec!["./a.js", "b", "./c.js"],
+        );
+        let ret = collect_imports(
+            SourceType::mjs(),
+            "import.meta.resolve(name); require.resolve(name);",
+            None,
+            &CollectOptions::default(),
+        );
+        assert!(matches!(
+            ret.errors.as_slice(),
+            [
+                AffectedError::UnsupportedDynamicImport { .. },
+                AffectedError::InvalidRequireArguments { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_triple_slash_references() {
+        let source_text = r#"/// <reference path="./globals.d.ts" />
+/// <reference path="legacy.d.ts"/>
+/// <reference types="node" />
+/// <reference lib="dom" />
+// <reference path="./not-a-directive.d.ts" />
+/* <reference path="./block.d.ts" /> */
+import "./a";
+/// <reference path="./after-statement.d.ts" />
+/// <reference types="jest" />"#;
+        assert_imports(source_text, vec!["./globals.d.ts", "./legacy.d.ts", "./a"]);
+        let ret = collect_imports(
+            SourceType::ts(),
+            source_text,
+            None,
+            &CollectOptions::default(),
+        );
+        assert_eq!(ret.type_references, vec!["node"]);
+        let ret = collect_imports(
+            SourceType::ts(),
+            source_text,
+            None,
+            &CollectOptions {
+                ignore_type_imports: true,
+                ..CollectOptions::default()
+            },
+        );
+        assert_eq!(ret.imports_paths, vec!["./a"]);
+        assert!(ret.type_references.is_empty());
+    }
+
+    #[test]
+    fn test_jsdoc_import_types() {
+        let source_text = r#"/** @type {import('./config').Config} */
+const config = {};
+/**
+ * @typedef {import("x").Y} Y
+ * @typedef {import('./all')} All
+ * @param {Array<import('./item').Item>} items
+ */
+function f(items) {}
+/** @import { Options } from "./options" */
+/** @import * as types from './types' */
+/** @import { fromJSON, Transform } from './transform' */
+/** @import feta from './feta' */
+/** @import fromage from './cheese' */
+/* {import('./block-comment').X} */
+// {import('./line-comment').X}
+/** Mentions important things and reimport('./nope') */"#;
+        assert_imports(
+            source_text,
+            vec![
+                "./config",
+                "x",
+                "./all",
+                "./item",
+                "./options",
+                "./types",
+                "./transform",
+                "./feta",
+                "./cheese",
+            ],
+        );
+        let ret = collect_imports(
+            SourceType::mjs(),
+            source_text,
+            None,
+            &CollectOptions {
+                ignore_type_imports: true,
+                ..CollectOptions::default()
+            },
+        );
+        assert!(ret.imports_paths.is_empty());
+        let ret = collect_imports(
+            SourceType::ts(),
+            source_text,
+            None,
+            &CollectOptions::default(),
+        );
+        assert!(ret.imports_paths.is_empty());
+
+        let ret = collect_imports(
+            SourceType::mjs(),
+            source_text,
+            None,
+            &CollectOptions {
+                track_symbols: true,
+                ..CollectOptions::default()
+            },
+        );
+        let usages = ret.symbols.unwrap().usages;
+        assert_eq!(
+            usages["./config"].names,
+            BTreeSet::from(["Config".to_string()])
+        );
+        assert!(!usages["./config"].all);
+        assert!(usages["./all"].all);
+    }
 }
@@ -1,7 +1,34 @@
 //! # Sovra
 
 mod affected;
+mod ambient;
+mod cache;
 mod changeset;
+mod context;
+mod error;
+mod git;
+mod glob;
+mod graph;
+mod graphql;
+mod hook;
+mod implicit;
 mod imports;
+mod regexp;
+mod rules;
+mod sfc;
+mod styles;
+mod watch;
 
-pub use crate::affected::collect_affected;
+pub use crate::affected::{
+    collect_affected, collect_affected_with_options, try_collect_affected,
+    try_collect_affected_with_options, AffectedOptions, AffectedReason, AffectedReturn,
+    Explanation,
+};
+pub use crate::changeset::{ChangeKind, ChangedEntry};
+pub use crate::error::{AffectedError, ErrorSpan, InputError};
+pub use crate::git::{git_changes, GitChanges};
+pub use crate::graph::DependencyGraph;
+pub use crate::hook::{HookResolution, ResolveHook, ResolveHookStage};
+pub use crate::implicit::ImplicitDependency;
+pub use crate::rules::ImportRule;
+pub use crate::watch::GraphWatcher;
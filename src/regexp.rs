@@ -0,0 +1,95 @@
+use fancy_regex::Regex;
+
+/// Tests each of `texts` against the JavaScript regular expression
+/// `/pattern/flags`. Returns `None` if the pattern doesn't compile, like
+/// ones using JavaScript-only syntax such as `[^]`, or if matching gives up
+/// on a text after too much backtracking, so callers can fall back to
+/// matching everything.
+pub fn regexp_matches(pattern: &str, flags: &str, texts: &[&str]) -> Option<Vec<bool>> {
+    let inline_flags: String = flags
+        .chars()
+        .filter(|flag| matches!(flag, 'i' | 'm' | 's'))
+        .collect();
+    let regex = if inline_flags.is_empty() {
+        Regex::new(pattern)
+    } else {
+        Regex::new(&format!("(?{inline_flags}){pattern}"))
+    }
+    .ok()?;
+    texts.iter().map(|text| regex.is_match(text).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, flags: &str, text: &str) -> bool {
+        regexp_matches(pattern, flags, &[text]).unwrap()[0]
+    }
+
+    #[test]
+    fn test_literals_and_anchors() {
+        assert!(is_match(r"\.spec\.js$", "", "./nested/suite.spec.js"));
+        assert!(!is_match(r"\.spec\.js$", "", "./suite.spec.jsx"));
+        assert!(is_match(r"^\.\/", "", "./a.js"));
+        assert!(!is_match(r"^a", "", "ba"));
+        assert!(is_match(r"^a", "m", "b\na"));
+    }
+
+    #[test]
+    fn test_quantifiers_and_groups() {
+        assert!(is_match(
+            r"^\.\/.*\.stories\.(js|tsx?)$",
+            "",
+            "./a/b.stories.ts"
+        ));
+        assert!(!is_match(
+            r"^\.\/.*\.stories\.(js|tsx?)$",
+            "",
+            "./a/b.stories.jsx"
+        ));
+        assert!(is_match(r"^a{2,3}$", "", "aaa"));
+        assert!(!is_match(r"^a{2,3}$", "", "aaaa"));
+        assert!(is_match(r"^(?:ab)+?c$", "", "ababc"));
+        assert!(is_match(r"^(a*)*b$", "", "aaab"));
+        assert!(is_match(r"^(?<name>\w+)\.js$", "", "index.js"));
+    }
+
+    #[test]
+    fn test_classes() {
+        assert!(is_match(r"^[a-c]\d\w$", "", "b1_"));
+        assert!(!is_match(r"^[^a-c]$", "", "b"));
+        assert!(is_match(r"^[A-C]+$", "i", "abc"));
+        assert!(is_match(r"\bfoo\b", "", "a foo b"));
+        assert!(!is_match(r"\bfoo\b", "", "afoob"));
+        assert!(is_match(r"^.$", "s", "\n"));
+        assert!(!is_match(r"^.$", "", "\n"));
+    }
+
+    #[test]
+    fn test_lookaround_and_back_references() {
+        assert!(is_match(r"^(?!.*test).*\.js$", "", "./a.js"));
+        assert!(!is_match(r"^(?!.*test).*\.js$", "", "./a.test.js"));
+        assert!(is_match(r"(?<!\.d)\.ts$", "", "./a.ts"));
+        assert!(!is_match(r"(?<!\.d)\.ts$", "", "./a.d.ts"));
+        assert!(is_match(r"^\.\/(\w+)\/\1\.js$", "", "./button/button.js"));
+        assert!(!is_match(r"^\.\/(\w+)\/\1\.js$", "", "./button/index.js"));
+    }
+
+    #[test]
+    fn test_catastrophic_backtracking() {
+        let text = format!("./{}!", "a".repeat(64));
+        assert!(!is_match(r"^\.\/(a+)+$", "", &text));
+        // Backtracking gives up past a limit instead of hanging.
+        assert_ne!(
+            regexp_matches(r"^\.\/(a+)+(?=b)", "", &[&text]),
+            Some(vec![true])
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert_eq!(regexp_matches(r"(", "", &["("]), None);
+        assert_eq!(regexp_matches(r"^[^]$", "", &["a"]), None);
+    }
+}
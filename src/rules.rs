@@ -0,0 +1,221 @@
+use oxc_ast::ast::{Argument, CallExpression, Expression, ObjectPropertyKind};
+
+use crate::error::InputError;
+
+/// A rule for collecting import specifiers from calls to custom loaders,
+/// like `loadComponent('widgets/Chart')`, that a require alias can't
+/// describe.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportRule {
+    /// The called function, as a dotted member chain like `loadComponent` or
+    /// `app.i18n.load`. A `*` segment matches any name.
+    pub callee: String,
+    /// Index of the argument holding the specifier.
+    pub argument: usize,
+    /// Dotted path of properties to read the specifier from when the
+    /// argument is an object literal, like `component` for
+    /// `route({ component: './Page' })`.
+    pub property: Option<String>,
+    /// Added in front of the matched string, like `@/widgets/` for
+    /// `loadComponent('Chart')`.
+    pub prefix: Option<String>,
+    /// Added after the matched string, like `.json`.
+    pub suffix: Option<String>,
+    /// The specifier is only referenced for its types, so it's skipped when
+    /// type imports are ignored.
+    pub type_only: bool,
+}
+
+impl ImportRule {
+    pub fn validate(&self) -> Result<(), InputError> {
+        let invalid = |reason: &str| InputError::InvalidImportRule {
+            callee: self.callee.clone(),
+            reason: reason.to_string(),
+        };
+        if self.callee.is_empty() {
+            return Err(invalid("callee must not be empty"));
+        }
+        if self.callee.split('.').any(str::is_empty) {
+            return Err(invalid("callee has empty segments"));
+        }
+        if self
+            .property
+            .as_ref()
+            .is_some_and(|property| property.split('.').any(str::is_empty))
+        {
+            return Err(invalid("property has empty segments"));
+        }
+        Ok(())
+    }
+
+    /// True if `callee`, as returned by [`callee_segments`], is this rule's.
+    pub fn matches_callee(&self, callee: &[&str]) -> bool {
+        let segments = self.callee.split('.');
+        segments.clone().count() == callee.len()
+            && segments
+                .zip(callee)
+                .all(|(segment, name)| segment == "*" || segment == *name)
+    }
+
+    /// Describes the argument [`ImportRule::specifier`] reads, for errors.
+    pub fn expected(&self) -> String {
+        match &self.property {
+            Some(property) => format!(
+                "an object literal with a string literal '{property}' property at argument index {}",
+                self.argument
+            ),
+            None => format!("a string literal argument at index {}", self.argument),
+        }
+    }
+
+    /// The specifier passed to `call`, or `None` if the argument or property
+    /// isn't a string literal.
+    pub fn specifier(&self, call: &CallExpression<'_>) -> Option<String> {
+        let mut expression = call
+            .arguments
+            .get(self.argument)
+            .and_then(Argument::as_expression)?;
+        for name in self.property.iter().flat_map(|p| p.split('.')) {
+            let Expression::ObjectExpression(object) = expression.without_parentheses() else {
+                return None;
+            };
+            expression = object
+                .properties
+                .iter()
+                .find_map(|property| match property {
+                    ObjectPropertyKind::ObjectProperty(property)
+                        if property.key.static_name().as_deref() == Some(name) =>
+                    {
+                        Some(&property.value)
+                    }
+                    _ => None,
+                })?;
+        }
+        let Expression::StringLiteral(literal) = expression.without_parentheses() else {
+            return None;
+        };
+        Some(format!(
+            "{}{}{}",
+            self.prefix.as_deref().unwrap_or_default(),
+            literal.value,
+            self.suffix.as_deref().unwrap_or_default()
+        ))
+    }
+}
+
+/// The names of a callee like `app.i18n.load`, or `None` if it isn't a
+/// chain of identifiers and static member accesses.
+pub fn callee_segments<'a>(callee: &'a Expression<'a>) -> Option<Vec<&'a str>> {
+    match callee.without_parentheses() {
+        Expression::Identifier(id) => Some(vec![id.name.as_str()]),
+        expression => {
+            let member = expression.as_member_expression()?;
+            let mut segments = callee_segments(member.object())?;
+            segments.push(member.static_property_name()?);
+            Some(segments)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oxc_allocator::Allocator;
+    use oxc_ast::ast::Statement;
+    use oxc_parser::Parser;
+    use oxc_span::SourceType;
+
+    use super::*;
+
+    fn rule(callee: &str) -> ImportRule {
+        ImportRule {
+            callee: callee.to_string(),
+            ..ImportRule::default()
+        }
+    }
+
+    /// Applies `rule` to the call expression statement `source_text`.
+    fn apply(rule: &ImportRule, source_text: &str) -> Option<Option<String>> {
+        let allocator = Allocator::default();
+        let program = Parser::new(&allocator, source_text, SourceType::mjs())
+            .parse()
+            .program;
+        let Some(Statement::ExpressionStatement(statement)) = program.body.first() else {
+            panic!("not an expression statement: {source_text}");
+        };
+        let Expression::CallExpression(call) = &statement.expression else {
+            panic!("not a call: {source_text}");
+        };
+        let callee = callee_segments(&call.callee)?;
+        rule.matches_callee(&callee).then(|| rule.specifier(call))
+    }
+
+    #[test]
+    fn test_callee() {
+        let load = rule("app.i18n.load");
+        assert_eq!(
+            apply(&load, "app.i18n.load('./fr.json')"),
+            Some(Some("./fr.json".to_string()))
+        );
+        assert_eq!(apply(&load, "i18n.load('./fr.json')"), None);
+        assert_eq!(apply(&load, "app.i18n.load.call('./fr.json')"), None);
+        assert_eq!(apply(&load, "app[i18n].load('./fr.json')"), None);
+        let wildcard = rule("*.load");
+        assert_eq!(
+            apply(&wildcard, "i18n.load('./fr.json')"),
+            Some(Some("./fr.json".to_string()))
+        );
+        assert_eq!(apply(&wildcard, "load('./fr.json')"), None);
+    }
+
+    #[test]
+    fn test_argument_and_template() {
+        let load_component = ImportRule {
+            argument: 1,
+            prefix: Some("@/widgets/".to_string()),
+            suffix: Some(".tsx".to_string()),
+            ..rule("loadComponent")
+        };
+        assert_eq!(
+            apply(&load_component, "loadComponent(registry, 'Chart', {})"),
+            Some(Some("@/widgets/Chart.tsx".to_string()))
+        );
+        assert_eq!(apply(&load_component, "loadComponent('Chart')"), Some(None));
+        assert_eq!(
+            apply(&load_component, "loadComponent(registry, name)"),
+            Some(None)
+        );
+    }
+
+    #[test]
+    fn test_property() {
+        let route = ImportRule {
+            property: Some("view.component".to_string()),
+            ..rule("route")
+        };
+        assert_eq!(
+            apply(
+                &route,
+                "route({ path: '/', view: { 'component': './Home' } })"
+            ),
+            Some(Some("./Home".to_string()))
+        );
+        assert_eq!(apply(&route, "route({ view: {} })"), Some(None));
+        assert_eq!(apply(&route, "route('./Home')"), Some(None));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(rule("app.i18n.load").validate().is_ok());
+        let err = rule("").validate().unwrap_err();
+        assert!(err.to_string().contains("callee must not be empty"));
+        let err = rule("app..load").validate().unwrap_err();
+        assert!(err.to_string().contains("callee has empty segments"));
+        let err = ImportRule {
+            property: Some("view.".to_string()),
+            ..rule("route")
+        }
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("property has empty segments"));
+    }
+}
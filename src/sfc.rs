@@ -0,0 +1,595 @@
+use std::path::Path;
+
+use oxc_span::SourceType;
+
+use crate::imports::ImportsReturn;
+use crate::styles::{collect_style_imports, StyleImport, StyleSyntax};
+
+/// A single-file component format with scripts and styles embedded in
+/// markup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SfcKind {
+    Vue,
+    Svelte,
+    Astro,
+    /// Markdown with JSX, importing components in top-level ESM blocks.
+    Mdx,
+    /// An HTML entry point, referencing scripts, stylesheets and images.
+    Html,
+}
+
+impl SfcKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "vue" => Some(SfcKind::Vue),
+            "svelte" => Some(SfcKind::Svelte),
+            "astro" => Some(SfcKind::Astro),
+            "mdx" => Some(SfcKind::Mdx),
+            "html" | "htm" => Some(SfcKind::Html),
+            _ => None,
+        }
+    }
+}
+
+/// The imports of a component: those of its script blocks, and the style
+/// imports of its style blocks along with the syntax they're written in.
+pub struct SfcImports {
+    pub script: ImportsReturn,
+    pub styles: Vec<(StyleImport, StyleSyntax)>,
+    /// Images, icons and preloaded files referenced from HTML, which aren't
+    /// errors when they can't be found.
+    pub assets: Vec<String>,
+}
+
+/// A `<script>` or `<style>` element.
+struct Element<'a> {
+    attributes: Vec<(&'a str, &'a str)>,
+    /// Byte offset of the content in the component.
+    offset: usize,
+    content: &'a str,
+}
+
+impl Element<'_> {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Parses the attributes of a start tag, without the tag name and brackets.
+pub fn parse_attributes(text: &str) -> Vec<(&str, &str)> {
+    let mut attributes = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (parsed, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = after[1..].find(quote).map_or(after.len(), |i| i + 1);
+                    (&after[1..end], &after[(end + 1).min(after.len())..])
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = parsed;
+            rest = remaining;
+        } else if name.is_empty() {
+            // A stray `/` of a self-closing tag.
+            rest = &rest[1..];
+        }
+        if !name.is_empty() {
+            attributes.push((name, value));
+        }
+        rest = rest.trim_start();
+    }
+    attributes
+}
+
+/// Finds the `>` ending a start tag, skipping quoted attribute values that
+/// may contain one, like `generic="T extends Record<string, X>"`.
+fn start_tag_end(attributes: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in attributes.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            (Some(q), c) if q == c => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Finds the top-level `<tag>` elements, skipping HTML comments. Void
+/// elements like `<link>` have no content or end tag.
+fn find_elements<'a>(text: &'a str, tag: &str, void: bool) -> Vec<Element<'a>> {
+    let lower = text.to_ascii_lowercase();
+    let open = format!("<{tag}");
+    let close = format!("</{tag}");
+    let mut elements = Vec::new();
+    let mut i = 0;
+    while let Some(start) = lower[i..].find('<').map(|s| i + s) {
+        if lower[start..].starts_with("<!--") {
+            i = lower[start..]
+                .find("-->")
+                .map_or(text.len(), |end| start + end + 3);
+            continue;
+        }
+        let after_name = start + open.len();
+        let is_tag = lower[start..].starts_with(&open)
+            && lower[after_name..].starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/');
+        if !is_tag {
+            i = start + 1;
+            continue;
+        }
+        let Some(tag_end) = start_tag_end(&lower[after_name..]).map(|end| after_name + end) else {
+            break;
+        };
+        let self_closing = lower[..tag_end].ends_with('/');
+        let attributes_end = if self_closing { tag_end - 1 } else { tag_end };
+        let attributes = parse_attributes(&text[after_name..attributes_end]);
+        let offset = tag_end + 1;
+        let (content, next) = if self_closing || void {
+            ("", offset)
+        } else {
+            match lower[offset..].find(&close) {
+                Some(end) => (&text[offset..offset + end], offset + end + close.len()),
+                None => (&text[offset..], text.len()),
+            }
+        };
+        elements.push(Element {
+            attributes,
+            offset,
+            content,
+        });
+        i = next;
+    }
+    elements
+}
+
+fn script_source_type(lang: Option<&str>, default: SourceType) -> SourceType {
+    match lang {
+        Some("ts") => SourceType::ts(),
+        Some("tsx") => SourceType::tsx(),
+        Some("jsx") => SourceType::jsx(),
+        Some("js") => SourceType::mjs(),
+        _ => default,
+    }
+}
+
+fn style_syntax(lang: Option<&str>) -> Option<StyleSyntax> {
+    match lang {
+        None | Some("css" | "postcss") => Some(StyleSyntax::Css),
+        Some("scss") => Some(StyleSyntax::Scss),
+        Some("sass") => Some(StyleSyntax::Sass),
+        Some("less") => Some(StyleSyntax::Less),
+        Some(_) => None,
+    }
+}
+
+/// Astro frontmatter: the TypeScript between the leading `---` fences.
+fn frontmatter(text: &str) -> Option<(usize, &str)> {
+    let start = text.len() - text.trim_start().len();
+    if !text[start..].starts_with("---") {
+        return None;
+    }
+    let body = start + 3;
+    let end = text[body..]
+        .find("\n---")
+        .map_or(text.len(), |end| body + end + 1);
+    Some((body, &text[body..end]))
+}
+
+/// Turns a URL in an HTML attribute into a specifier. Root-relative URLs
+/// are taken to be relative to the HTML file, which is usually the root of
+/// the app it's the entry point of.
+fn html_reference(url: &str) -> Option<String> {
+    let url = url.trim();
+    let has_scheme = url
+        .split_once(':')
+        .is_some_and(|(scheme, _)| scheme.chars().all(|c| c.is_ascii_alphanumeric()));
+    if url.is_empty() || has_scheme || url.starts_with("//") || url.starts_with('#') {
+        return None;
+    }
+    if url.starts_with("./") || url.starts_with("../") {
+        return Some(url.to_string());
+    }
+    Some(format!("./{}", url.trim_start_matches('/')))
+}
+
+/// MDX ESM blocks: paragraphs starting with `import` or `export` at the
+/// start of a line, outside code fences.
+fn esm_blocks(text: &str) -> Vec<(usize, &str)> {
+    let mut blocks = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut block_start: Option<usize> = None;
+    let mut previous_blank = true;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let blank = trimmed.is_empty();
+        if let Some(start) = block_start {
+            if blank {
+                blocks.push((start, &text[start..offset]));
+                block_start = None;
+            }
+        } else if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if let Some(marker) = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| line.trim_start().starts_with(marker))
+        {
+            fence = Some(marker);
+        } else if previous_blank
+            && ["import", "export"].iter().any(|keyword| {
+                line.strip_prefix(keyword)
+                    .is_some_and(|rest| rest.starts_with([' ', '{', '*', '\t']))
+            })
+        {
+            block_start = Some(offset);
+        }
+        previous_blank = blank;
+        offset += line.len();
+    }
+    if let Some(start) = block_start {
+        blocks.push((start, &text[start..]));
+    }
+    blocks
+}
+
+/// Collects the imports of the script and style blocks of a component.
+/// `collect` is called for every script with its source type, and the
+/// spans of its errors are moved to the block's place in the component.
+pub fn collect_sfc_imports(
+    kind: SfcKind,
+    text: &str,
+    collect: impl Fn(SourceType, &str) -> ImportsReturn,
+) -> SfcImports {
+    let mut script = ImportsReturn::default();
+    let mut styles: Vec<(StyleImport, StyleSyntax)> = Vec::new();
+    let mut assets: Vec<String> = Vec::new();
+    let add_script = |script: &mut ImportsReturn, source_type, offset: usize, content| {
+        let mut block: ImportsReturn = collect(source_type, content);
+        for error in block.errors.iter_mut() {
+            error.shift_span(offset as u32);
+        }
+        script.extend(block);
+    };
+
+    let markup = match kind {
+        SfcKind::Astro => match frontmatter(text) {
+            Some((offset, content)) => {
+                add_script(&mut script, SourceType::ts(), offset, content);
+                &text[offset + content.len()..]
+            }
+            None => text,
+        },
+        SfcKind::Mdx => {
+            for (offset, content) in esm_blocks(text) {
+                add_script(&mut script, SourceType::jsx(), offset, content);
+            }
+            // Script and style elements in MDX are part of the content.
+            ""
+        }
+        SfcKind::Vue | SfcKind::Svelte | SfcKind::Html => text,
+    };
+    let markup_offset = text.len() - markup.len();
+    let default_source_type = match kind {
+        SfcKind::Astro => SourceType::ts(),
+        SfcKind::Vue | SfcKind::Svelte | SfcKind::Mdx | SfcKind::Html => SourceType::mjs(),
+    };
+    for element in find_elements(markup, "script", false) {
+        // Astro leaves inline scripts as they are.
+        if kind == SfcKind::Astro && element.attribute("is:inline").is_some() {
+            continue;
+        }
+        let source_type = script_source_type(element.attribute("lang"), default_source_type);
+        if kind == SfcKind::Html {
+            if let Some(src) = element.attribute("src").and_then(html_reference) {
+                script.imports_paths.push(src);
+            }
+            // Only module scripts can import anything.
+            if element.attribute("type") != Some("module") {
+                continue;
+            }
+        } else if let Some(src) = element.attribute("src") {
+            script.imports_paths.push(src.to_string());
+        }
+        add_script(
+            &mut script,
+            source_type,
+            markup_offset + element.offset,
+            element.content,
+        );
+    }
+    for element in find_elements(markup, "style", false) {
+        let Some(syntax) = style_syntax(element.attribute("lang")) else {
+            continue;
+        };
+        if let Some(src) = element.attribute("src") {
+            let import = StyleImport {
+                specifier: src.to_string(),
+                asset: false,
+            };
+            styles.push((import, syntax));
+        }
+        styles.extend(
+            collect_style_imports(syntax, element.content)
+                .into_iter()
+                .map(|import| (import, syntax)),
+        );
+    }
+
+    if kind == SfcKind::Html {
+        for element in find_elements(markup, "link", true) {
+            let Some(href) = element.attribute("href").and_then(html_reference) else {
+                continue;
+            };
+            let rel = element.attribute("rel").unwrap_or_default();
+            let has_rel = |name: &str| {
+                rel.split_whitespace()
+                    .any(|rel| rel.eq_ignore_ascii_case(name))
+            };
+            if has_rel("stylesheet") {
+                let import = StyleImport {
+                    specifier: href,
+                    asset: false,
+                };
+                styles.push((import, StyleSyntax::Css));
+            } else if has_rel("modulepreload") {
+                script.imports_paths.push(href);
+            } else if has_rel("preload") || has_rel("icon") {
+                assets.push(href);
+            }
+        }
+        for element in find_elements(markup, "img", true) {
+            if let Some(src) = element.attribute("src").and_then(html_reference) {
+                assets.push(src);
+            }
+        }
+    }
+
+    // Components are imported through their default export.
+    if let Some(symbols) = &mut script.symbols {
+        symbols.exports.insert("default".to_string());
+    }
+    script.imports_paths.sort();
+    script.imports_paths.dedup();
+    assets.sort();
+    assets.dedup();
+    SfcImports {
+        script,
+        styles,
+        assets,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{AffectedError, ErrorSpan};
+    use crate::imports::{collect_imports, CollectOptions};
+
+    fn collect(kind: SfcKind, text: &str) -> SfcImports {
+        collect_sfc_imports(kind, text, |source_type, text| {
+            collect_imports(source_type, text, None, &CollectOptions::default())
+        })
+    }
+
+    fn style_specifiers(imports: &SfcImports) -> Vec<(&str, StyleSyntax)> {
+        imports
+            .styles
+            .iter()
+            .map(|(import, syntax)| (import.specifier.as_str(), *syntax))
+            .collect()
+    }
+
+    #[test]
+    fn test_vue() {
+        let imports = collect(
+            SfcKind::Vue,
+            r#"<template>
+  <!-- <script>import "./commented";</script> -->
+  <Icon />
+</template>
+<script lang="ts">
+import type { Props } from "./types";
+export default { name: "Button" };
+</script>
+<script setup lang="ts">
+import Icon from "./Icon.vue";
+const props = defineProps<Props>();
+</script>
+<style lang="scss" scoped>
+@use "../styles/colors";
+</style>
+<style src="./button.css"></style>"#,
+        );
+        assert!(imports.script.errors.is_empty());
+        assert_eq!(imports.script.imports_paths, vec!["./Icon.vue", "./types"]);
+        assert_eq!(
+            style_specifiers(&imports),
+            vec![
+                ("../styles/colors", StyleSyntax::Scss),
+                ("./button.css", StyleSyntax::Css),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_vue_generic_attribute() {
+        let imports = collect(
+            SfcKind::Vue,
+            r#"<script setup lang="ts" generic="T extends Record<string, X>">
+import type { X } from "./types";
+import Row from './Row.vue';
+defineProps<{ items: T[] }>();
+</script>"#,
+        );
+        assert!(
+            imports.script.errors.is_empty(),
+            "{:?}",
+            imports.script.errors
+        );
+        assert_eq!(imports.script.imports_paths, vec!["./Row.vue", "./types"]);
+    }
+
+    #[test]
+    fn test_svelte() {
+        let imports = collect(
+            SfcKind::Svelte,
+            r#"<script context="module">
+  export const prerender = true;
+  import { load } from "./load.js";
+</script>
+<script>
+  import Card from "./Card.svelte";
+</script>
+<Card />
+<style>
+  .card { background: url(./bg.png); }
+</style>"#,
+        );
+        assert_eq!(
+            imports.script.imports_paths,
+            vec!["./Card.svelte", "./load.js"]
+        );
+        assert_eq!(
+            style_specifiers(&imports),
+            vec![("./bg.png", StyleSyntax::Css)]
+        );
+    }
+
+    #[test]
+    fn test_astro() {
+        let imports = collect(
+            SfcKind::Astro,
+            r#"---
+import Layout from "../layouts/Layout.astro";
+const title: string = "Home";
+---
+<Layout title={title}>
+  <script>
+    import { track } from "./analytics";
+  </script>
+  <script is:inline src="https://example.com/a.js"></script>
+</Layout>"#,
+        );
+        assert!(imports.script.errors.is_empty());
+        assert_eq!(
+            imports.script.imports_paths,
+            vec!["../layouts/Layout.astro", "./analytics"]
+        );
+    }
+
+    #[test]
+    fn test_error_spans_point_into_component() {
+        let text = "<template></template>\n<script>\nimport(name);\n</script>";
+        let imports = collect(SfcKind::Vue, text);
+        let start = text.find("import(").unwrap() as u32;
+        assert_eq!(
+            imports.script.errors,
+            vec![AffectedError::UnsupportedDynamicImport {
+                file: "unknown file".to_string(),
+                span: ErrorSpan {
+                    start,
+                    end: start + "import(name)".len() as u32,
+                },
+                call: "import".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_mdx() {
+        let text = r#"import { Meta } from "@storybook/blocks";
+import { Button } from "./Button";
+
+<Meta title="Button" />
+
+# Button
+
+Importing `Button`:
+
+```js
+import { Button } from "./not-collected";
+```
+
+export const Example = () => <Button primary />;
+
+Text that mentions import { x } from "./prose" inline.
+"#;
+        let imports = collect(SfcKind::Mdx, text);
+        assert!(
+            imports.script.errors.is_empty(),
+            "{:?}",
+            imports.script.errors
+        );
+        assert_eq!(
+            imports.script.imports_paths,
+            vec!["./Button", "@storybook/blocks"]
+        );
+        let blocks = esm_blocks(text);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].0, text.find("export const").unwrap());
+    }
+
+    #[test]
+    fn test_html() {
+        let imports = collect(
+            SfcKind::Html,
+            r#"<!doctype html>
+<html>
+  <head>
+    <link rel="stylesheet" href="/src/index.css" />
+    <link rel="icon" href="favicon.svg">
+    <link rel="preconnect" href="https://fonts.googleapis.com">
+    <link rel="modulepreload" href="/src/chunk.js">
+    <link rel="preload" href="/fonts/brand.woff2" as="font">
+    <link rel="manifest" href="/manifest.json">
+    <link rel="canonical" href="/about">
+    <script src="./vendor/legacy.js"></script>
+    <script>window.dataLayer = [];</script>
+  </head>
+  <body>
+    <!-- <script type="module" src="/src/old.ts"></script> -->
+    <img src="../assets/logo.png" alt="">
+    <script type="module" src="/src/main.ts"></script>
+    <script type="module">
+      import { hydrate } from "./src/hydrate.js";
+    </script>
+  </body>
+</html>"#,
+        );
+        assert!(imports.script.errors.is_empty());
+        assert_eq!(
+            imports.script.imports_paths,
+            vec![
+                "./src/chunk.js",
+                "./src/hydrate.js",
+                "./src/main.ts",
+                "./vendor/legacy.js",
+            ]
+        );
+        assert_eq!(
+            imports.assets,
+            vec!["../assets/logo.png", "./favicon.svg", "./fonts/brand.woff2"]
+        );
+        assert_eq!(
+            style_specifiers(&imports),
+            vec![("./src/index.css", StyleSyntax::Css)]
+        );
+    }
+}
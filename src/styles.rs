@@ -0,0 +1,368 @@
+use std::path::{Path, PathBuf};
+
+use oxc_resolver::{ResolveError, Resolver};
+use serde::{Deserialize, Serialize};
+
+use crate::changeset::normalize;
+
+/// A stylesheet language with its own import rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StyleSyntax {
+    Css,
+    Scss,
+    /// The indented Sass syntax.
+    Sass,
+    Less,
+}
+
+impl StyleSyntax {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "css" => Some(StyleSyntax::Css),
+            "scss" => Some(StyleSyntax::Scss),
+            "sass" => Some(StyleSyntax::Sass),
+            "less" => Some(StyleSyntax::Less),
+            _ => None,
+        }
+    }
+
+    fn is_sass(self) -> bool {
+        matches!(self, StyleSyntax::Scss | StyleSyntax::Sass)
+    }
+}
+
+/// A specifier collected from a stylesheet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyleImport {
+    pub specifier: String,
+    /// Referenced with `url()` outside of an import rule, like an image or a
+    /// font. Assets that don't resolve aren't reported as errors, since
+    /// bundlers often serve them from elsewhere.
+    pub asset: bool,
+}
+
+/// Collects the specifiers of `@import` rules, Sass `@use` and `@forward`
+/// rules and `url()` references. URLs with a scheme, root-relative URLs and
+/// interpolated specifiers are skipped, as they don't name a file that can
+/// be found from the stylesheet.
+pub fn collect_style_imports(syntax: StyleSyntax, text: &str) -> Vec<StyleImport> {
+    let bytes = text.as_bytes();
+    let mut imports: Vec<StyleImport> = Vec::new();
+    let mut add = |specifier: &str, asset: bool| {
+        let specifier = specifier.trim();
+        if is_file_reference(specifier) && !imports.iter().any(|i| i.specifier == specifier) {
+            imports.push(StyleImport {
+                specifier: specifier.to_string(),
+                asset,
+            });
+        }
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = text[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + end + 4);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') && syntax != StyleSyntax::Css => {
+                i = text[i..].find('\n').map_or(bytes.len(), |end| i + end);
+            }
+            b'"' | b'\'' => i = read_string(text, i).1,
+            b'@' => {
+                let start = i + 1;
+                let end = start
+                    + text[start..]
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+                        .unwrap_or(text.len() - start);
+                let keyword = &text[start..end];
+                let is_import = keyword == "import"
+                    || (syntax.is_sass() && (keyword == "use" || keyword == "forward"));
+                i = end;
+                if !is_import {
+                    continue;
+                }
+                // Only `@import` accepts several comma separated URLs.
+                let single = keyword != "import";
+                // The indented syntax also allows them to be unquoted.
+                let unquoted = syntax == StyleSyntax::Sass && !single;
+                let mut url_start = true;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b';' | b'{' | b'}' => break,
+                        b'\n' if syntax == StyleSyntax::Sass => break,
+                        b',' => {
+                            url_start = true;
+                            i += 1;
+                        }
+                        b'"' | b'\'' => {
+                            let (value, end) = read_string(text, i);
+                            add(value, false);
+                            i = end;
+                            if single {
+                                break;
+                            }
+                            url_start = false;
+                        }
+                        _ if starts_with_url(text, i) => {
+                            let (value, end) = read_url(text, i);
+                            add(value, false);
+                            i = end;
+                            url_start = false;
+                        }
+                        c if unquoted && url_start && !c.is_ascii_whitespace() => {
+                            let end = text[i..]
+                                .find([',', '\n'])
+                                .map_or(bytes.len(), |end| i + end);
+                            add(&text[i..end], false);
+                            i = end;
+                            url_start = false;
+                        }
+                        _ => i += 1,
+                    }
+                }
+            }
+            _ if starts_with_url(text, i) => {
+                let (value, end) = read_url(text, i);
+                add(value, true);
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+    imports
+}
+
+/// Reads the string starting with the quote at `start`. Returns its value
+/// and the index after the closing quote.
+fn read_string(text: &str, start: usize) -> (&str, usize) {
+    let quote = text.as_bytes()[start];
+    let bytes = text.as_bytes();
+    let mut i = start + 1;
+    while i < bytes.len() && bytes[i] != quote && bytes[i] != b'\n' {
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+    let end = i.min(bytes.len());
+    (&text[start + 1..end], (end + 1).min(bytes.len()))
+}
+
+fn starts_with_url(text: &str, i: usize) -> bool {
+    text.get(i..i + 4)
+        .is_some_and(|s| s.eq_ignore_ascii_case("url("))
+        && !text[..i]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Reads the `url()` starting at `start`. Returns its value and the index
+/// after the closing parenthesis.
+fn read_url(text: &str, start: usize) -> (&str, usize) {
+    let open = start + 4;
+    let value_start = open + (text[open..].len() - text[open..].trim_start().len());
+    match text.as_bytes().get(value_start) {
+        Some(b'"' | b'\'') => {
+            let (value, end) = read_string(text, value_start);
+            let close = text[end..].find(')').map_or(text.len(), |i| end + i + 1);
+            (value, close)
+        }
+        _ => match text[value_start..].find(')') {
+            Some(i) => (&text[value_start..value_start + i], value_start + i + 1),
+            None => ("", text.len()),
+        },
+    }
+}
+
+fn is_file_reference(specifier: &str) -> bool {
+    let has_scheme = specifier
+        .split_once(':')
+        .is_some_and(|(scheme, _)| scheme.chars().all(|c| c.is_ascii_alphanumeric()));
+    !(specifier.is_empty()
+        || has_scheme
+        || specifier.starts_with('/')
+        || specifier.starts_with('#')
+        || specifier.contains("#{")
+        || specifier.contains("@{")
+        || specifier.contains('$'))
+}
+
+/// The paths `specifier` may refer to, most specific first. Sass tries
+/// `.scss`, `.sass` and `.css` files, their `_partial` variants and
+/// `_index` files; Less adds a missing `.less` extension.
+fn candidates(syntax: StyleSyntax, specifier: &str) -> Vec<String> {
+    let mut candidates = vec![specifier.to_string()];
+    let (dir, name) = match specifier.rsplit_once('/') {
+        Some((dir, name)) => (format!("{dir}/"), name),
+        None => (String::new(), specifier),
+    };
+    let extension = Path::new(name).extension().and_then(|e| e.to_str());
+    match syntax {
+        StyleSyntax::Scss | StyleSyntax::Sass => {
+            if matches!(extension, Some("scss" | "sass" | "css")) {
+                candidates.push(format!("{dir}_{name}"));
+            } else {
+                for extension in ["scss", "sass", "css"] {
+                    candidates.push(format!("{specifier}.{extension}"));
+                    candidates.push(format!("{dir}_{name}.{extension}"));
+                }
+                for extension in ["scss", "sass", "css"] {
+                    candidates.push(format!("{specifier}/_index.{extension}"));
+                    candidates.push(format!("{specifier}/index.{extension}"));
+                }
+            }
+        }
+        StyleSyntax::Less if extension.is_none() => candidates.push(format!("{specifier}.less")),
+        StyleSyntax::Css | StyleSyntax::Less => {}
+    }
+    candidates
+}
+
+/// Resolves a specifier collected from a stylesheet in `dir`. Specifiers are
+/// relative to the stylesheet first. Bare specifiers, and ones with the
+/// webpack `~` prefix, then go through `resolver` to find packages.
+pub fn resolve_style_import(
+    syntax: StyleSyntax,
+    resolver: &Resolver,
+    dir: &Path,
+    specifier: &str,
+) -> Result<PathBuf, ResolveError> {
+    let (specifier, is_module) = match specifier.strip_prefix('~') {
+        Some(specifier) => (specifier, true),
+        None => (specifier, false),
+    };
+    let candidates = candidates(syntax, specifier);
+    if !is_module {
+        let found = candidates
+            .iter()
+            .map(|candidate| normalize(&dir.join(candidate)))
+            .find(|path| path.is_file());
+        if let Some(path) = found {
+            return Ok(path);
+        }
+    }
+    let is_relative = specifier.starts_with("./") || specifier.starts_with("../");
+    if !is_relative {
+        let has_extension = Path::new(specifier).extension().is_some();
+        for candidate in candidates.iter().skip(usize::from(!has_extension)) {
+            if let Ok(resolution) = resolver.resolve(dir, candidate) {
+                return Ok(resolution.into_path_buf());
+            }
+        }
+    }
+    Err(ResolveError::NotFound(specifier.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use oxc_resolver::ResolveOptions;
+
+    use super::*;
+
+    fn specifiers(syntax: StyleSyntax, text: &str) -> Vec<String> {
+        collect_style_imports(syntax, text)
+            .into_iter()
+            .map(|import| import.specifier)
+            .collect()
+    }
+
+    #[test]
+    fn test_css() {
+        assert_eq!(
+            specifiers(
+                StyleSyntax::Css,
+                r#"@import "reset.css";
+                @import url('./theme.css') screen;
+                /* @import "commented.css"; */
+                .logo { background: url(images/logo.png) no-repeat; }
+                .icon { background: url("data:image/png;base64,AAAA"), url(https://a.com/b.png); }
+                .font { src: url(/fonts/a.woff2), url(#filter); content: "url(nope.png)"; }"#
+            ),
+            vec!["reset.css", "./theme.css", "images/logo.png"]
+        );
+        assert_eq!(
+            collect_style_imports(
+                StyleSyntax::Css,
+                "@import url(a.css); .a { background: url(a.png); }"
+            ),
+            vec![
+                StyleImport {
+                    specifier: "a.css".to_string(),
+                    asset: false,
+                },
+                StyleImport {
+                    specifier: "a.png".to_string(),
+                    asset: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scss() {
+        assert_eq!(
+            specifiers(
+                StyleSyntax::Scss,
+                r##"@use "sass:math";
+                @use "../shared/colors" as c;
+                @forward "src/list" hide list-reset;
+                @import "a", "b";
+                // @use "commented";
+                .a { background: url("#{$base}/img.png"); }"##
+            ),
+            vec!["../shared/colors", "src/list", "a", "b"]
+        );
+    }
+
+    #[test]
+    fn test_indented_sass() {
+        assert_eq!(
+            specifiers(
+                StyleSyntax::Sass,
+                "@use 'colors'\n@import variables, mixins/buttons\n@import 'print.css' print\n.a\n  color: red\n"
+            ),
+            vec!["colors", "variables", "mixins/buttons", "print.css"]
+        );
+    }
+
+    #[test]
+    fn test_less() {
+        assert_eq!(
+            specifiers(
+                StyleSyntax::Less,
+                r#"@import (reference) "mixins";
+                @import (css) url("print.css");
+                @var: ~"@{path}/a.less";
+                // @import "commented";"#
+            ),
+            vec!["mixins", "print.css"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_sass_partials() {
+        let dir = env::current_dir().unwrap().join("fixtures/styles");
+        let resolver = Resolver::new(ResolveOptions::default());
+        let resolve = |syntax, specifier| {
+            resolve_style_import(syntax, &resolver, &dir, specifier)
+                .map(|path| path.strip_prefix(&dir).unwrap().to_path_buf())
+        };
+        assert_eq!(
+            resolve(StyleSyntax::Scss, "shared/colors"),
+            Ok(PathBuf::from("shared/_colors.scss"))
+        );
+        assert_eq!(
+            resolve(StyleSyntax::Scss, "shared"),
+            Ok(PathBuf::from("shared/_index.scss"))
+        );
+        assert_eq!(
+            resolve(StyleSyntax::Less, "./theme"),
+            Ok(PathBuf::from("theme.less"))
+        );
+        assert_eq!(
+            resolve(StyleSyntax::Css, "./missing.css"),
+            Err(ResolveError::NotFound("./missing.css".to_string()))
+        );
+    }
+}
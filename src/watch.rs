@@ -0,0 +1,262 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::affected::AffectedReturn;
+use crate::changeset::ChangedEntry;
+use crate::error::InputError;
+use crate::graph::DependencyGraph;
+
+/// Keeps a [`DependencyGraph`] up to date with edits in the working tree.
+///
+/// Files in the graph are polled for modification; every modified file is
+/// re-scanned in place and added to the changeset, on top of the `changes`
+/// the watcher was created with. Files that are not in the graph can't affect
+/// any test until a file in the graph starts importing them, so only those
+/// are polled, along with imports that failed to resolve: once one of them
+/// resolves, the created file is added to the changeset as well.
+pub struct GraphWatcher {
+    graph: DependencyGraph,
+    changes: Vec<ChangedEntry>,
+    modified: Vec<PathBuf>,
+    mtimes: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl GraphWatcher {
+    /// Creates the watcher. Panics on invalid `changes` entries, see
+    /// [`GraphWatcher::try_new`].
+    pub fn new(graph: DependencyGraph, changes: Vec<&str>) -> Self {
+        Self::try_new(graph, changes).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_new(graph: DependencyGraph, changes: Vec<&str>) -> Result<Self, InputError> {
+        let changes = graph.parse_changes(changes)?;
+        let mut watcher = GraphWatcher {
+            graph,
+            changes,
+            modified: Vec::new(),
+            mtimes: HashMap::new(),
+        };
+        watcher.track_new_files();
+        Ok(watcher)
+    }
+
+    pub fn graph(&self) -> &DependencyGraph {
+        &self.graph
+    }
+
+    /// Starts polling files that were added to the graph and stops polling
+    /// the ones that were dropped from it.
+    fn track_new_files(&mut self) {
+        let files: HashSet<&Path> = self.graph.files().collect();
+        self.mtimes.retain(|p, _| files.contains(p.as_path()));
+        for file in files {
+            if !self.mtimes.contains_key(file) {
+                self.mtimes.insert(file.to_path_buf(), mtime(file));
+            }
+        }
+    }
+
+    /// Checks every file in the graph once and re-scans the ones that were
+    /// modified, created or deleted since the last poll, and the ones with
+    /// imports of files created since. Returns the modified and created files.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut modified: Vec<PathBuf> = self
+            .mtimes
+            .iter_mut()
+            .filter_map(|(path, last)| {
+                let current = mtime(path);
+                if current == *last {
+                    return None;
+                }
+                *last = current;
+                Some(path.clone())
+            })
+            .collect();
+        if !modified.is_empty() {
+            self.graph
+                .update_files(modified.iter().filter_map(|p| p.to_str()).collect());
+        }
+        for created in self.graph.update_unresolved() {
+            if !modified.contains(&created) {
+                modified.push(created);
+            }
+        }
+        if modified.is_empty() {
+            return modified;
+        }
+
+        self.track_new_files();
+        for path in modified.iter() {
+            if !self.modified.contains(path) {
+                self.modified.push(path.clone());
+            }
+        }
+        modified
+    }
+
+    /// The test files affected by the initial changes plus every file
+    /// modified since the watcher was created.
+    pub fn affected(&self) -> AffectedReturn {
+        let mut changes = self.changes.clone();
+        changes.extend(self.modified.iter().cloned().map(ChangedEntry::modified));
+        self.graph.affected_by(changes)
+    }
+
+    /// Reports the affected test files, then polls every `interval` and
+    /// reports them again whenever a file changed, until `on_change` breaks.
+    pub fn watch<F>(&mut self, interval: Duration, mut on_change: F)
+    where
+        F: FnMut(&AffectedReturn) -> ControlFlow<()>,
+    {
+        if on_change(&self.affected()).is_break() {
+            return;
+        }
+        loop {
+            thread::sleep(interval);
+            if self.poll().is_empty() {
+                continue;
+            }
+            if on_change(&self.affected()).is_break() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs::File, process};
+
+    use oxc_resolver::{ResolveOptions, Resolver};
+
+    use super::*;
+    use crate::affected::AffectedOptions;
+
+    fn touch(path: &Path, contents: &str, mtime: SystemTime) {
+        fs::write(path, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_watcher_tracks_edits() {
+        let dir = env::temp_dir().join(format!("sovra-watch-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let spec = dir.join("suite.spec.js");
+        let other_spec = dir.join("other.spec.js");
+        let a = dir.join("a.js");
+        let b = dir.join("b.js");
+        let epoch = SystemTime::UNIX_EPOCH;
+        touch(&a, "", epoch);
+        touch(&b, "", epoch);
+        touch(&spec, "import './a.js';", epoch);
+        touch(&other_spec, "import './b.js';", epoch);
+
+        let spec_str = spec.to_str().unwrap();
+        let other_spec_str = other_spec.to_str().unwrap();
+        let graph = DependencyGraph::new(
+            vec![spec_str, other_spec_str],
+            Resolver::new(ResolveOptions::default()),
+            &AffectedOptions::default(),
+        );
+        let mut watcher = GraphWatcher::new(graph, vec![]);
+        assert!(watcher.poll().is_empty());
+        assert!(watcher.affected().files.is_empty());
+
+        touch(&a, "export const a = 1;", epoch + Duration::from_secs(1));
+        assert_eq!(watcher.poll(), vec![a.clone()]);
+        assert_eq!(watcher.affected().files, vec![spec_str]);
+
+        // `suite.spec.js` now also depends on `b.js`.
+        touch(
+            &spec,
+            "import './a.js'; import './b.js';",
+            epoch + Duration::from_secs(1),
+        );
+        assert_eq!(watcher.poll(), vec![spec.clone()]);
+        touch(&b, "export const b = 1;", epoch + Duration::from_secs(1));
+        assert_eq!(watcher.poll(), vec![b.clone()]);
+        let mut files = watcher.affected().files;
+        files.sort();
+        assert_eq!(files, vec![other_spec_str, spec_str]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_watcher_resolves_created_files() {
+        let dir = env::temp_dir().join(format!("sovra-watch-created-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let spec = dir.join("suite.spec.js");
+        let c = dir.join("c.js");
+        touch(&spec, "import './c.js';", SystemTime::UNIX_EPOCH);
+
+        let spec_str = spec.to_str().unwrap();
+        let graph = DependencyGraph::new(
+            vec![spec_str],
+            Resolver::new(ResolveOptions::default()),
+            &AffectedOptions::default(),
+        );
+        let mut watcher = GraphWatcher::new(graph, vec![]);
+        assert!(watcher.poll().is_empty());
+        assert_eq!(watcher.affected().errors.len(), 1);
+
+        fs::write(&c, "").unwrap();
+        assert_eq!(watcher.poll(), vec![c.clone()]);
+        let ret = watcher.affected();
+        assert!(ret.errors.is_empty());
+        assert_eq!(ret.files, vec![spec_str]);
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_watcher_invalid_changes() {
+        let graph = DependencyGraph::new(
+            vec!["fixtures/nested/module.spec.js"],
+            Resolver::new(ResolveOptions::default()),
+            &AffectedOptions::default(),
+        );
+        let err = GraphWatcher::try_new(graph, vec!["npm:"]).err().unwrap();
+        assert_eq!(
+            err,
+            InputError::InvalidChangedEntry {
+                entry: "npm:".to_string(),
+                reason: "missing package name after 'npm:'".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_watch_stops_on_break() {
+        let graph = DependencyGraph::new(
+            vec!["fixtures/nested/module.spec.js"],
+            Resolver::new(ResolveOptions::default()),
+            &AffectedOptions::default(),
+        );
+        let mut watcher = GraphWatcher::new(graph, vec!["fixtures/nested/module.js"]);
+        let mut reported = vec![];
+        watcher.watch(Duration::from_millis(1), |ret| {
+            reported.push(ret.files.clone());
+            ControlFlow::Break(())
+        });
+        assert_eq!(reported, vec![vec!["fixtures/nested/module.spec.js"]]);
+    }
+}